@@ -1,24 +1,119 @@
 use std::{
+    collections::HashMap,
     ffi::OsStr,
-    fs::{create_dir, File},
-    io::{Read, Write},
-    path::PathBuf,
+    fs::{read, read_link, read_to_string, symlink_metadata},
+    io,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
     process::exit,
 };
 
-use directories::BaseDirs;
-use template_engine::TemplateEngine;
+use clap::ArgMatches;
+use rayon::prelude::*;
+use template_engine::{analysis::is_templated, TemplateEngine};
 
-pub fn apply() {
-    // Create list of output paths
-    let mut destination_array: Vec<Option<PathBuf>> = Vec::new();
+use crate::{
+    backup::Backup,
+    config::DeployStrategy,
+    conflict::{Conflict, ConflictPolicy, Resolution},
+    diff::print_diff,
+    hooks::run_hooks,
+    permissions::FileAttributes,
+    render::{condition_holds, template_engine, variables_engine, variables_hash},
+    selection::Selection,
+    state::{file_hash, hash, StateFile},
+    transaction::Transaction,
+    utils::{as_text, data_dir, write_config},
+};
+
+/// Exit code of `apply --dry-run` if there are changes that would be written.
+const CHANGES_PENDING: i32 = 2;
+
+pub fn apply(matches: &ArgMatches) {
+    let force = matches.get_flag("force");
+    let dry_run = matches.get_flag("dry_run");
+    let no_hooks = matches.get_flag("no_hooks");
+    // Safe unwrap: The argument has a default value and only accepts known policies
+    let on_conflict = matches
+        .get_one::<String>("on_conflict")
+        .unwrap()
+        .parse::<ConflictPolicy>()
+        .unwrap();
+
+    // Destinations that receive a copy of their rendered source
+    let mut planned_copies: Vec<PlannedCopy> = Vec::new();
+
+    // Contents of the sources without a destination, which define the variables of the templates
+    let mut variable_sources: Vec<(String, bool)> = Vec::new();
 
-    // Create array of file paths and contents
-    let mut content_array: Vec<(String, bool)> = Vec::new();
+    // Commands of the destinations that run once they changed
+    let mut change_hooks: HashMap<PathBuf, Vec<String>> = HashMap::new();
+
+    // Sources that are linked instead of rendered
+    let mut link_array: Vec<(PathBuf, PathBuf, DeployStrategy)> = Vec::new();
+
+    // Existing destinations not created by DotMan, which are backed up before they are replaced
+    let mut foreign_destinations: Vec<PathBuf> = Vec::new();
+
+    // Edited or foreign destinations that are left as they are
+    let mut skipped_count = 0;
+    // Conflicting destinations whose local version is kept instead of the source
+    let mut kept_count = 0;
 
     let mut config_struct = crate::get_config_file_content();
 
-    for (source, destination) in config_struct.paths_iter() {
+    let mut state = StateFile::read();
+    let migrated = state.migrate(&mut config_struct.applied_paths);
+
+    let selection = Selection::from_matches(matches);
+
+    let linked_files = config_struct.linked_files();
+
+    // Conditions can use the variables of the source files, which are only evaluated if needed
+    let variables_engine = match linked_files.iter().any(|x| x.condition.is_some()) {
+        true => match variables_engine(&config_struct) {
+            Ok(x) => Some(x),
+            Err(e) => {
+                eprintln!(
+                    "ERROR: Received the following error while parsing a config:\n{}",
+                    e
+                );
+                exit(1);
+            }
+        },
+        false => None,
+    };
+
+    for linked_file in &linked_files {
+        // Unselected sources without a destination still define variables for the selected templates
+        if !linked_file.is_variable_source() && !selection.matches(linked_file) {
+            continue;
+        }
+
+        // Files that do not belong on this machine are skipped without a warning
+        if let Some(variables_engine) = &variables_engine {
+            match condition_holds(linked_file, variables_engine) {
+                Ok(true) => (),
+                Ok(false) => continue,
+                Err(e) => {
+                    println!(
+                        "WARNING: Failed to evaluate the condition of `{:#?}`, because of the following error:\n{}\nSkipping...",
+                        linked_file.source, e
+                    );
+                    continue;
+                }
+            }
+        }
+
+        let source = linked_file.source.clone();
+        let destination = linked_file.destination.clone();
+
+        if let Some(destination) = &destination {
+            if !linked_file.on_change.is_empty() {
+                change_hooks.insert(destination.clone(), linked_file.on_change.clone());
+            }
+        }
+
         if !source.exists() {
             println!(
                 "WARNING: Source file with path `{:#?}` does not exist. Skipping...",
@@ -27,42 +122,60 @@ pub fn apply() {
             continue;
         }
 
-        let mut source_content = String::new();
-
-        let mut source_handle =
-            File::open(&source).expect("ERROR: Failed to open source file, but path exists.");
+        if !linked_file.strategy.is_copy() {
+            let Some(destination) = destination else {
+                println!(
+                    "WARNING: Config with path `{:#?}` has no link location. Skipping...",
+                    source
+                );
+                continue;
+            };
 
-        match source_handle.read_to_string(&mut source_content) {
-            Ok(_) => (),
-            Err(_) => {
-                eprintln!(
-                    "ERROR: Failed to read source file with path `{:#?}`, but path exists.",
+            if linked_file.mode.is_some() || linked_file.owner.is_some() {
+                println!(
+                    "WARNING: `mode` and `owner` of `{:#?}` are ignored, because links share the permissions of their source.",
                     source
                 );
-                exit(1);
             }
-        }
 
-        if source_content.is_empty() {
-            println!(
-                "WARNING: Source file at path `{:#?}` is empty. Skipping...",
-                source
-            );
+            link_array.push((source, destination, linked_file.strategy));
             continue;
         }
 
+        let attributes = match FileAttributes::of(linked_file) {
+            Ok(x) => x,
+            Err(e) => {
+                println!(
+                    "WARNING: Failed to determine the permissions of `{:#?}`: {}. Skipping...",
+                    source, e
+                );
+                continue;
+            }
+        };
+
         match destination {
             Some(destination) => {
-                if destination.exists() {
-                    println!(
-                        "WARNING: Link destination with path `{:#?}` already exists. Skipping...",
-                        destination
-                    );
-                    continue;
+                // Files written by a previous apply are updated, anything else is left alone
+                if (destination.exists() || is_symlink(&destination))
+                    && !state.is_applied(&destination)
+                {
+                    if !force {
+                        println!(
+                            "WARNING: Link destination with path `{:#?}` already exists and was not created by DotMan. Use `--force` to back it up and replace it. Skipping...",
+                            destination
+                        );
+                        skipped_count += 1;
+                        continue;
+                    }
+
+                    foreign_destinations.push(destination.clone());
                 }
 
-                content_array.push((source_content, false));
-                destination_array.push(Some(destination));
+                planned_copies.push(PlannedCopy {
+                    destination,
+                    source,
+                    attributes,
+                });
             }
             None => {
                 if source.extension() != Some(OsStr::new("te")) {
@@ -73,96 +186,721 @@ pub fn apply() {
                     continue;
                 }
 
-                content_array.push((source_content, true));
-                destination_array.push(None);
+                let Some(source_content) = as_text(&read_source(&source)) else {
+                    println!(
+                        "WARNING: Source file at path `{:#?}` is not a text file. Skipping...",
+                        source
+                    );
+                    continue;
+                };
+
+                // Empty sources define no variables, while empty linked files are deployed like any other file
+                if source_content.is_empty() {
+                    continue;
+                }
+
+                variable_sources.push((source_content, true));
+            }
+        }
+    }
+
+    let mut template_engine = template_engine(&config_struct);
+
+    // Every template is rendered on top of the variables of all source files
+    if let Err(e) = template_engine.evaluate_files(variable_sources) {
+        eprintln!(
+            "ERROR: Received the following error while parsing a config:\n{}",
+            e
+        );
+        exit(1);
+    }
+
+    // Reading, hashing and rendering every file is independent of the other files
+    let prepared_copies = planned_copies
+        .par_iter()
+        .map(|x| x.prepare(&template_engine, &state))
+        .collect::<Vec<io::Result<PreparedCopy>>>();
+
+    // Destinations edited since the last apply, which are only replaced if their source changed as well
+    let mut drifted_destinations: Vec<PathBuf> = Vec::new();
+
+    // Rendered templates, which may still conflict with local edits
+    let mut rendered_files: Vec<CopiedFile> = Vec::new();
+
+    // Sources that are not text, which are copied without rendering them
+    let mut binary_files: Vec<CopiedFile> = Vec::new();
+
+    for (planned_copy, prepared_copy) in planned_copies.into_iter().zip(prepared_copies) {
+        let PlannedCopy {
+            destination,
+            source,
+            attributes,
+        } = planned_copy;
+
+        let (content, is_binary, drifted, variables_hash) = match prepared_copy {
+            Ok(PreparedCopy::UpToDate) => continue,
+            Ok(PreparedCopy::Changed {
+                content,
+                is_binary,
+                drifted,
+                variables_hash,
+            }) => (content, is_binary, drifted, variables_hash),
+            Err(e) => {
+                eprintln!(
+                    "ERROR: Failed to read source file with path `{:#?}`, because of the following error:\n{}",
+                    source, e
+                );
+                exit(1);
+            }
+        };
+
+        // Manual edits of deployed files are never overwritten silently
+        if drifted {
+            if force {
+                foreign_destinations.push(destination.clone());
+            } else if is_binary {
+                // Binary files can not be merged, so there is no conflict resolution
+                println!(
+                    "WARNING: `{:#?}` was edited since it was last applied. Use `--force` to back it up and replace it. Skipping...",
+                    destination
+                );
+                skipped_count += 1;
+                continue;
+            } else {
+                drifted_destinations.push(destination.clone());
+            }
+        }
+
+        let content = match content {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!(
+                    "ERROR: Received the following error while rendering `{:#?}`:\n{}",
+                    source, e
+                );
+                exit(1);
+            }
+        };
+
+        let copied_file = CopiedFile {
+            destination,
+            source,
+            recorded_content: content.clone(),
+            content,
+            attributes,
+            variables_hash,
+            is_binary,
+        };
+
+        match is_binary {
+            true => binary_files.push(copied_file),
+            false => rendered_files.push(copied_file),
+        }
+    }
+
+    let mut copied_files: Vec<CopiedFile> = Vec::new();
+
+    for mut copied_file in rendered_files {
+        let destination = copied_file.destination.clone();
+
+        if !drifted_destinations.contains(&destination) {
+            copied_files.push(copied_file);
+            continue;
+        }
+
+        // Only the local edits differ from the last apply, so there is nothing to deploy
+        if state.get(&destination).and_then(|x| x.hash.as_ref())
+            == Some(&hash(&copied_file.content))
+        {
+            println!(
+                "WARNING: `{:#?}` was edited since it was last applied. Use `--force` to back it up and replace it. Skipping...",
+                destination
+            );
+            skipped_count += 1;
+            continue;
+        }
+
+        let local = match read_to_string(&destination) {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!(
+                    "ERROR: Failed to read destination `{:#?}`, because of the following error:\n{}",
+                    destination, e
+                );
+                exit(1);
+            }
+        };
+
+        // Safe unwrap: Rendered templates are text
+        let rendered = String::from_utf8(copied_file.content.clone()).unwrap();
+
+        let conflict = Conflict {
+            destination: &destination,
+            local: &local,
+            base: state.base(&destination),
+            rendered: &rendered,
+        };
+
+        // A dry run never prompts
+        let policy = match on_conflict {
+            ConflictPolicy::Ask if dry_run => ConflictPolicy::Skip,
+            policy => policy,
+        };
+
+        match conflict.resolve(policy, config_struct.merge_tool.as_deref()) {
+            Resolution::KeepLocal if dry_run => {
+                println!(
+                    "INFO: Would keep the local version of `{:#?}`.",
+                    destination
+                );
+            }
+            Resolution::KeepLocal => {
+                // The current source version is not offered again
+                state.record_kept(
+                    &destination,
+                    &copied_file.source,
+                    rendered.as_bytes(),
+                    copied_file.variables_hash,
+                );
+                println!("INFO: Kept the local version of `{:#?}`.", destination);
+                kept_count += 1;
+            }
+            Resolution::TakeSource => {
+                foreign_destinations.push(destination);
+                copied_files.push(copied_file);
+            }
+            // Merged destinations record the rendered source, so their local edits still count as edits
+            Resolution::Merged(merged) => {
+                copied_file.content = merged.into_bytes();
+                foreign_destinations.push(destination);
+                copied_files.push(copied_file);
+            }
+            Resolution::Skip => {
+                println!(
+                    "WARNING: `{:#?}` was edited since it was last applied and its source changed as well. Use `--on-conflict` or `--force` to resolve the conflict. Skipping...",
+                    destination
+                );
+                skipped_count += 1;
+            }
+        }
+    }
+
+    copied_files.extend(binary_files);
+
+    // Links that are not deployed correctly, which are skipped if they are foreign files
+    let mut pending_links = Vec::new();
+
+    for (source, destination, strategy) in link_array {
+        let link_state = link_state(&source, &destination, strategy);
+
+        match link_state {
+            // Links deployed before the state file existed have no recorded source
+            LinkState::Correct => {
+                if state
+                    .get(&destination)
+                    .is_none_or(|x| x.source.as_os_str().is_empty())
+                {
+                    state.record(&destination, &source, None);
+                }
+            }
+            LinkState::Missing => pending_links.push((source, destination, strategy, link_state)),
+            _ if state.is_applied(&destination) => {
+                pending_links.push((source, destination, strategy, link_state))
+            }
+            _ if force => {
+                foreign_destinations.push(destination.clone());
+                pending_links.push((source, destination, strategy, link_state))
+            }
+            _ => {
+                println!(
+                    "WARNING: Link destination with path `{:#?}` already exists and was not created by DotMan. Use `--force` to back it up and replace it. Skipping...",
+                    destination
+                );
+                skipped_count += 1;
+            }
+        }
+    }
+
+    if dry_run {
+        preview(
+            copied_files,
+            pending_links,
+            &foreign_destinations,
+            skipped_count,
+        );
+    }
+
+    let hook_timeout = config_struct.hooks.timeout();
+
+    if !no_hooks
+        && !run_hooks(
+            &config_struct.hooks.pre_apply,
+            &config_struct.home_path,
+            hook_timeout,
+        )
+    {
+        eprintln!("ERROR: A pre-apply hook failed. No file was changed.");
+        exit(1);
+    }
+
+    let mut backup = Backup::new(data_dir());
+    let transaction = Transaction::default();
+
+    // Create a file for each 'compiled' config at the desired location and write the content to it.
+    // The files are written in parallel and reported in order afterwards
+    let written = copied_files
+        .par_iter()
+        .map(|copied_file| {
+            let location = &copied_file.destination;
+
+            // Unchanged output is not written again and foreign files with the same content are adopted as they are
+            if !is_symlink(location)
+                && read(location).is_ok_and(|x| x == copied_file.content)
+                && copied_file.attributes.matches(location)
+            {
+                return Ok(false);
             }
+
+            let foreign = foreign_destinations.contains(location);
+
+            transaction
+                .write(
+                    location,
+                    &copied_file.content,
+                    &copied_file.attributes,
+                    foreign,
+                )
+                .map(|_| true)
+        })
+        .collect::<Vec<io::Result<bool>>>();
+
+    // Destinations that changed, in the order of the config
+    let mut changed_paths: Vec<PathBuf> = Vec::new();
+
+    for (copied_file, written) in copied_files.into_iter().zip(written) {
+        let CopiedFile {
+            destination: location,
+            source,
+            recorded_content,
+            variables_hash,
+            is_binary,
+            ..
+        } = copied_file;
+
+        // Binary files are never merged, so only their hash is recorded and no base is stored
+        let record = |state: &mut StateFile| match is_binary {
+            true => state.record(&location, &source, Some(hash(&recorded_content))),
+            false => state.record_render(
+                &location,
+                &source,
+                &recorded_content,
+                variables_hash.clone(),
+            ),
+        };
+
+        match written {
+            Ok(true) => {
+                record(&mut state);
+
+                println!("INFO: Updated `{:#?}`.", location);
+                changed_paths.push(location);
+            }
+            Ok(false) => {
+                let recorded_hash = hash(&recorded_content);
+
+                if state.get(&location).is_none_or(|x| {
+                    x.hash.as_ref() != Some(&recorded_hash)
+                        || x.source_hash != file_hash(&source)
+                        || x.variables_hash != variables_hash
+                }) {
+                    record(&mut state);
+                }
+            }
+            Err(e) => abort(
+                transaction,
+                format!(
+                    "ERROR: Failed to write content to file at `{:#?}`, because of the following error:\n{}",
+                    location, e
+                ),
+            ),
+        }
+    }
+
+    for (source, destination, strategy, link_state) in pending_links {
+        if let LinkState::Unexpected(Some(target)) = &link_state {
+            println!(
+                "WARNING: Symlink `{:#?}` pointed to `{:#?}` instead of its source. Repairing...",
+                destination, target
+            );
+        }
+
+        let foreign = foreign_destinations.contains(&destination);
+
+        let result = source.canonicalize().and_then(|source| match strategy {
+            DeployStrategy::Hardlink => transaction.hard_link(&source, &destination, foreign),
+            _ => transaction.symlink(&source, &destination, foreign),
+        });
+
+        if let Err(e) = result {
+            abort(
+                transaction,
+                format!(
+                    "ERROR: Failed to link `{:#?}` to `{:#?}`, because of the following error:\n{}",
+                    destination, source, e
+                ),
+            );
+        }
+
+        state.record(&destination, &source, None);
+
+        println!("INFO: Linked `{:#?}` to `{:#?}`.", destination, source);
+        changed_paths.push(destination);
+    }
+
+    let updated_count = transaction.len();
+
+    // Every command runs once, in the order of the changed files
+    let mut changed_hooks: Vec<String> = Vec::new();
+    for hook in changed_paths
+        .iter()
+        .filter_map(|x| change_hooks.get(x))
+        .flatten()
+    {
+        if !changed_hooks.contains(hook) {
+            changed_hooks.push(hook.clone());
         }
     }
-    // Parse all source files through the template engine
-    let compiled_configs = match TemplateEngine::parse_files(content_array) {
+
+    // Removed again by `unapply` once they are empty
+    state.created_dirs.extend(
+        transaction
+            .created_dirs()
+            .iter()
+            .filter_map(|x| std::path::absolute(x).ok()),
+    );
+
+    transaction.commit(&mut backup);
+    state.write();
+    // Drops the migrated `applied_paths`. The config is left untouched otherwise, which keeps its comments
+    if migrated {
+        write_config(&config_struct);
+    }
+
+    if kept_count > 0 {
+        println!(
+            "INFO: Kept the local version of {} file(s) instead of applying their source.",
+            kept_count
+        );
+    }
+
+    match (updated_count, skipped_count) {
+        (0, 0) if kept_count > 0 => (),
+        (0, 0) => println!("INFO: All configs are up to date."),
+        (0, _) => println!(
+            "INFO: No configs were applied. {} file(s) were skipped, see the warnings above.",
+            skipped_count
+        ),
+        (_, 0) => println!("INFO: Succesfully applied your configs."),
+        _ => println!(
+            "INFO: Applied your configs, but {} file(s) were skipped, see the warnings above.",
+            skipped_count
+        ),
+    }
+
+    if no_hooks {
+        return;
+    }
+
+    let hooks_succeeded = run_hooks(&changed_hooks, &config_struct.home_path, hook_timeout)
+        & run_hooks(
+            &config_struct.hooks.post_apply,
+            &config_struct.home_path,
+            hook_timeout,
+        );
+
+    if !hooks_succeeded {
+        eprintln!("ERROR: At least one hook failed. Your configs were applied anyway.");
+        exit(1);
+    }
+}
+
+/// Reads a source file or exits.
+fn read_source(source: &Path) -> Vec<u8> {
+    match read(source) {
         Ok(x) => x,
         Err(e) => {
             eprintln!(
-                "ERROR: Received the following error while parsing a config:\n{}",
-                e.to_string()
+                "ERROR: Failed to read source file with path `{:#?}`, because of the following error:\n{}",
+                source, e
             );
             exit(1);
         }
-    };
-
-    if compiled_configs.len() != destination_array.len() {
-        eprintln!("ERROR: Number of configs and destinations is not equal after templating.");
-        exit(1);
     }
+}
 
-    // Check if data directory exists
-    let base_dirs = match BaseDirs::new() {
-        Some(x) => x,
-        None => {
-            eprintln!("ERROR: Failed to find local data directory.");
-            exit(1);
+/// A managed file that is copied to its destination.
+struct PlannedCopy {
+    destination: PathBuf,
+    source: PathBuf,
+    attributes: FileAttributes,
+}
+
+enum PreparedCopy {
+    /// Neither the source, the variables it uses nor the destination changed since the last apply
+    UpToDate,
+    Changed {
+        /// The rendered template, or the source itself if it is binary
+        content: anyhow::Result<Vec<u8>>,
+        is_binary: bool,
+        /// Whether the destination was edited since it was last applied
+        drifted: bool,
+        variables_hash: Option<String>,
+    },
+}
+
+impl PlannedCopy {
+    /// Reads and renders the source, unless the destination is still up to date.
+    fn prepare(
+        &self,
+        template_engine: &TemplateEngine,
+        state: &StateFile,
+    ) -> io::Result<PreparedCopy> {
+        let source_content = read(&self.source)?;
+        let text = as_text(&source_content);
+        let is_binary = text.is_none();
+
+        // Files without expressions render to the same content for any variables
+        let variables_hash = text
+            .as_deref()
+            .filter(|x| is_templated(x))
+            .map(|x| variables_hash(template_engine, x));
+
+        let recorded = state.get(&self.destination);
+        let recorded_hash = recorded.and_then(|x| x.hash.as_ref());
+        let destination_hash = file_hash(&self.destination);
+
+        // Kept local versions stay as they are until the source or its variables change
+        let up_to_date = recorded.is_some_and(|x| {
+            (recorded_hash.is_some() && recorded_hash == destination_hash.as_ref()
+                || x.kept_hash.is_some() && x.kept_hash == destination_hash)
+                && x.source_hash == Some(hash(&source_content))
+                && x.variables_hash == variables_hash
+        }) && !is_symlink(&self.destination)
+            && self.attributes.matches(&self.destination);
+
+        if up_to_date {
+            return Ok(PreparedCopy::UpToDate);
         }
+
+        // Files without a recorded hash or that do not exist anymore never count as drifted
+        let drifted =
+            recorded_hash.is_some_and(|x| destination_hash.as_ref().is_some_and(|y| x != y));
+
+        let content = match text {
+            Some(text) => template_engine
+                .render_template(&text)
+                .map(String::into_bytes),
+            None => Ok(source_content),
+        };
+
+        Ok(PreparedCopy::Changed {
+            content,
+            is_binary,
+            drifted,
+            variables_hash,
+        })
+    }
+}
+
+/// A rendered template or binary file that is copied to its destination.
+struct CopiedFile {
+    destination: PathBuf,
+    source: PathBuf,
+    content: Vec<u8>,
+    /// The content recorded as applied, which differs from `content` for merged destinations
+    recorded_content: Vec<u8>,
+    attributes: FileAttributes,
+    /// Hash of the variables the content was rendered with
+    variables_hash: Option<String>,
+    is_binary: bool,
+}
+
+/// Prints the error, restores every file replaced so far and exits.
+fn abort(transaction: Transaction, message: String) -> ! {
+    eprintln!("{}", message);
+
+    let change_count = transaction.len();
+    transaction.rollback();
+
+    if change_count > 0 {
+        eprintln!(
+            "INFO: Rolled back the {} file(s) replaced before the error.",
+            change_count
+        );
+    }
+
+    exit(1);
+}
+
+fn is_symlink(path: &Path) -> bool {
+    symlink_metadata(path).is_ok_and(|x| x.file_type().is_symlink())
+}
+
+/// The state of a symlinked or hardlinked destination.
+pub enum LinkState {
+    Correct,
+    Missing,
+    /// The destination is another file or a symlink to the contained target
+    Unexpected(Option<PathBuf>),
+}
+
+pub fn link_state(source: &Path, destination: &Path, strategy: DeployStrategy) -> LinkState {
+    let Ok(metadata) = symlink_metadata(destination) else {
+        return LinkState::Missing;
     };
 
-    let data_dir = base_dirs.data_dir();
+    match strategy {
+        DeployStrategy::Symlink if metadata.file_type().is_symlink() => {
+            let target = read_link(destination).ok();
+
+            if target.as_deref().and_then(|x| x.canonicalize().ok()) == source.canonicalize().ok() {
+                LinkState::Correct
+            } else {
+                LinkState::Unexpected(target)
+            }
+        }
+        DeployStrategy::Hardlink => match source.metadata() {
+            Ok(source_metadata)
+                if source_metadata.dev() == metadata.dev()
+                    && source_metadata.ino() == metadata.ino() =>
+            {
+                LinkState::Correct
+            }
+            _ => LinkState::Unexpected(None),
+        },
+        _ => LinkState::Unexpected(None),
+    }
+}
+
+/// Prints the changes `apply` would make without writing anything and exits.
+/// The exit code is `CHANGES_PENDING` if at least one file would be created or modified.
+fn preview(
+    copied_files: Vec<CopiedFile>,
+    pending_links: Vec<(PathBuf, PathBuf, DeployStrategy, LinkState)>,
+    foreign_destinations: &[PathBuf],
+    skipped_count: usize,
+) -> ! {
+    let mut created_count = 0;
+    let mut modified_count = 0;
 
-    let mut config_data_dir = data_dir.to_path_buf();
-    config_data_dir.push("dotman");
+    for destination in foreign_destinations {
+        println!(
+            "INFO: `{:#?}` was not created by DotMan or edited since it was last applied and would be moved into a backup.",
+            destination
+        );
+    }
 
-    if !config_data_dir.exists() {
-        match create_dir(&config_data_dir) {
-            Ok(_) => println!(
-                "WARNING: Data directory was not found. Created new directory at `{:#?}`",
-                config_data_dir
-            ),
+    for (source, destination, strategy, state) in pending_links {
+        let kind = match strategy {
+            DeployStrategy::Hardlink => "hardlink",
+            _ => "symlink",
+        };
 
-            Err(e) => {
-                eprintln!("ERROR: Data directory was not found. Failed to create a new directory at `{:#?}` with error `{:#?}`.", config_data_dir, e);
-                exit(1);
+        match state {
+            LinkState::Missing => {
+                println!(
+                    "INFO: Would create {} `{:#?}` to `{:#?}`.",
+                    kind, destination, source
+                );
+                created_count += 1;
+            }
+            LinkState::Unexpected(Some(target)) => {
+                println!(
+                    "INFO: Would repair {} `{:#?}`, which points to `{:#?}` instead of `{:#?}`.",
+                    kind, destination, target, source
+                );
+                modified_count += 1;
+            }
+            _ => {
+                println!(
+                    "INFO: Would replace `{:#?}` with a {} to `{:#?}`.",
+                    destination, kind, source
+                );
+                modified_count += 1;
             }
         }
     }
 
-    // Create a file for each 'compiled' config at the desired location and write the content to it
-    for (content, location) in compiled_configs
-        .into_iter()
-        .zip(destination_array.clone().into_iter())
-    {
-        if location.is_none() {
+    for copied_file in copied_files {
+        let location = &copied_file.destination;
+        let content = &copied_file.content;
+        let attributes = &copied_file.attributes;
+
+        let label = location.to_string_lossy();
+
+        if is_symlink(location) {
+            println!(
+                "INFO: Would replace symlink `{:#?}` with a rendered copy:",
+                location
+            );
+            print_content_diff(&[], content, "/dev/null", &label);
+            modified_count += 1;
             continue;
         }
 
-        let location = location.unwrap();
-
-        if location.exists() {
-            eprintln!("ERROR: Destination `{:#?} already exists.`", location);
-            exit(1);
+        if !location.exists() {
+            println!("INFO: Would create `{:#?}`:", location);
+            print_content_diff(&[], content, "/dev/null", &label);
+            created_count += 1;
+            continue;
         }
 
-        let mut file_handle = match File::create(&location) {
-            Ok(f) => f,
+        let current_content = match read(location) {
+            Ok(x) => x,
             Err(e) => {
                 eprintln!(
-                    "ERROR: Failed to create file at `{:#?}`, because of the following error:\n{}",
-                    location,
-                    e.to_string()
+                    "ERROR: Failed to read destination `{:#?}`, because of the following error:\n{}",
+                    location, e
                 );
                 exit(1);
             }
         };
 
-        match file_handle.write_all(content.as_bytes()) {
-            Ok(_) => (),
-            Err(e) => {
-                eprintln!("ERROR: Failed to write content to file at `{:#?}`, because of the following error:\n{}", location, e.to_string());
-                exit(1);
-            }
+        if current_content != *content {
+            println!("INFO: Would modify `{:#?}`:", location);
+            print_content_diff(&current_content, content, &label, &label);
+            modified_count += 1;
+        } else if !attributes.matches(location) {
+            println!(
+                "INFO: Would change the permissions of `{:#?}` to `{:04o}`.",
+                location, attributes.mode
+            );
+            modified_count += 1;
+        }
+    }
+
+    if created_count + modified_count == 0 {
+        match skipped_count {
+            0 => println!("INFO: All configs are up to date."),
+            _ => println!(
+                "INFO: No file would be changed. {} file(s) would be skipped, see the warnings above.",
+                skipped_count
+            ),
         }
+        exit(0);
     }
 
-    config_struct
-        .applied_paths
-        .extend(destination_array.into_iter().filter_map(|x| x));
+    println!(
+        "INFO: {} file(s) would be created, {} file(s) would be modified and {} file(s) would be skipped.",
+        created_count, modified_count, skipped_count
+    );
+    exit(CHANGES_PENDING);
+}
 
-    println!("INFO: Succesfully applied your configs.");
+/// Prints a diff of text content, or only that binary content differs like `git diff` does.
+fn print_content_diff(old: &[u8], new: &[u8], old_label: &str, new_label: &str) {
+    match (as_text(old), as_text(new)) {
+        (Some(old), Some(new)) => print_diff(&old, &new, old_label, new_label),
+        _ => println!("Binary files {} and {} differ", old_label, new_label),
+    }
 }
@@ -0,0 +1,24 @@
+use std::process::exit;
+
+use crate::{
+    backup::{backup_files, backup_ids, backups_dir},
+    utils::data_dir,
+};
+
+pub fn list() {
+    let data_dir = data_dir();
+    let ids = backup_ids(&data_dir);
+
+    if ids.is_empty() {
+        println!("INFO: There are no backups.\nBackups are created when `apply --force` replaces a file that was not created by DotMan.");
+        exit(0);
+    }
+
+    for id in ids {
+        println!("Backup: {}", id);
+
+        for file in backup_files(&backups_dir(&data_dir).join(&id)) {
+            println!("    {:#?}", file);
+        }
+    }
+}
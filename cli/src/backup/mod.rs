@@ -0,0 +1,151 @@
+use std::{
+    fs::{copy, create_dir_all, read_dir, remove_dir_all, remove_file, rename, symlink_metadata},
+    io,
+    path::{Path, PathBuf},
+    process::exit,
+};
+
+use clap::ArgMatches;
+use walkdir::WalkDir;
+
+mod list;
+mod restore;
+
+pub fn backup(matches: &ArgMatches) {
+    match matches.subcommand() {
+        Some(("list", _sub_matches)) => list::list(),
+        Some(("restore", sub_matches)) => restore::restore(sub_matches),
+        Some((subcommand, _)) => {
+            eprintln!("Unknown subcommand {}", subcommand);
+            exit(1);
+        }
+        None => {
+            eprintln!("No subcommand provided");
+            exit(1);
+        }
+    }
+}
+
+/// Directory holding one timestamped directory per backup.
+pub fn backups_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("backups")
+}
+
+/// The names of all backups, oldest first.
+pub fn backup_ids(data_dir: &Path) -> Vec<String> {
+    let Ok(entries) = read_dir(backups_dir(data_dir)) else {
+        return Vec::new();
+    };
+
+    let mut ids = entries
+        .filter_map(Result::ok)
+        .filter(|x| x.path().is_dir())
+        .map(|x| x.file_name().to_string_lossy().to_string())
+        .collect::<Vec<String>>();
+
+    ids.sort();
+    ids
+}
+
+/// The original paths of the files stored in a backup.
+/// A backup mirrors the absolute paths of its files, so `/home/user/.bashrc` is stored as `<backup>/home/user/.bashrc`.
+pub fn backup_files(backup_dir: &Path) -> Vec<PathBuf> {
+    WalkDir::new(backup_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|x| !x.file_type().is_dir())
+        .filter_map(|x| {
+            x.path()
+                .strip_prefix(backup_dir)
+                .ok()
+                .map(|x| Path::new("/").join(x))
+        })
+        .collect()
+}
+
+/// Where the backup in `backup_dir` stores the file with the absolute path `path`.
+pub fn backup_path(backup_dir: &Path, path: &Path) -> PathBuf {
+    backup_dir.join(path.strip_prefix("/").unwrap_or(path))
+}
+
+/// The most recent backup of the file with the absolute path `path`.
+pub fn latest_backup_of(data_dir: &Path, path: &Path) -> Option<PathBuf> {
+    backup_ids(data_dir)
+        .into_iter()
+        .rev()
+        .map(|id| backups_dir(data_dir).join(id))
+        .find(|backup_dir| symlink_metadata(backup_path(backup_dir, path)).is_ok())
+}
+
+/// Removes a backup once all of its files have been restored.
+pub fn prune_backup(backup_dir: &Path) {
+    if !backup_files(backup_dir).is_empty() {
+        return;
+    }
+
+    if let Err(e) = remove_dir_all(backup_dir) {
+        println!(
+            "WARNING: Failed to remove the empty backup `{:#?}`, because of the following error:\n{}",
+            backup_dir, e
+        );
+    }
+}
+
+/// Moves a file, falling back to copying it if it is on another file system.
+pub fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        create_dir_all(parent)?;
+    }
+
+    if rename(from, to).is_err() {
+        copy(from, to)?;
+        remove_file(from)?;
+    }
+
+    Ok(())
+}
+
+/// A backup created by `apply` for the destinations it replaces.
+/// The timestamped directory is only created once the first file is stored.
+pub struct Backup {
+    data_dir: PathBuf,
+    dir: Option<PathBuf>,
+}
+
+impl Backup {
+    pub fn new(data_dir: PathBuf) -> Self {
+        Self {
+            data_dir,
+            dir: None,
+        }
+    }
+
+    fn dir(&mut self) -> PathBuf {
+        if let Some(dir) = &self.dir {
+            return dir.clone();
+        }
+
+        let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+        let mut dir = backups_dir(&self.data_dir).join(&timestamp);
+
+        // Several backups can be created within the same second
+        let mut counter = 1;
+        while dir.exists() {
+            dir = backups_dir(&self.data_dir).join(format!("{}_{}", timestamp, counter));
+            counter += 1;
+        }
+
+        self.dir = Some(dir.clone());
+        dir
+    }
+
+    /// Moves `file`, the previous version of `path`, into the backup and returns its new location.
+    pub fn store(&mut self, path: &Path, file: &Path) -> io::Result<PathBuf> {
+        let path = std::path::absolute(path)?;
+        let backup_path = backup_path(&self.dir(), &path);
+
+        move_file(file, &backup_path)?;
+
+        Ok(backup_path)
+    }
+}
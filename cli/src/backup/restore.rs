@@ -0,0 +1,116 @@
+use std::{
+    fs::{remove_file, symlink_metadata},
+    path::PathBuf,
+    process::exit,
+};
+
+use clap::ArgMatches;
+
+use crate::{
+    backup::{backup_files, backup_ids, backup_path, backups_dir, prune_backup, Backup},
+    state::StateFile,
+    transaction::Transaction,
+    utils::{data_dir, get_config_file_content, write_config},
+};
+
+pub fn restore(sub_matches: &ArgMatches) {
+    let id = sub_matches
+        .get_one::<String>("id")
+        .expect("ERROR: Invalid backup supplied.");
+
+    let selected_paths = sub_matches
+        .get_many::<PathBuf>("paths")
+        .map(|x| x.collect::<Vec<&PathBuf>>());
+
+    let force = sub_matches.get_flag("force");
+
+    let data_dir = data_dir();
+
+    if !backup_ids(&data_dir).contains(id) {
+        eprintln!("ERROR: There is no backup `{}`.\nYou can print all backups by using the `backup list` command.", id);
+        exit(1);
+    }
+
+    let backup_dir = backups_dir(&data_dir).join(id);
+
+    let mut config_struct = get_config_file_content();
+    let mut state = StateFile::read();
+    let migrated = state.migrate(&mut config_struct.applied_paths);
+    let mut failed = false;
+
+    let mut backup = Backup::new(data_dir.clone());
+    let transaction = Transaction::default();
+    let mut restored_paths: Vec<PathBuf> = Vec::new();
+
+    for path in backup_files(&backup_dir) {
+        if selected_paths.as_ref().is_some_and(|x| {
+            !x.iter()
+                .any(|x| std::path::absolute(x).is_ok_and(|x| x == path))
+        }) {
+            continue;
+        }
+
+        let exists = symlink_metadata(&path).is_ok();
+
+        // Only files deployed by DotMan may be replaced by their original
+        if exists && !state.is_applied(&path) {
+            eprintln!(
+                "ERROR: `{:#?}` exists and was not created by DotMan. Skipping...",
+                path
+            );
+            failed = true;
+            continue;
+        }
+
+        // Local edits are only discarded on request and kept in a new backup
+        let drifted = exists && state.has_drifted(&path);
+        if drifted && !force {
+            eprintln!(
+                "ERROR: `{:#?}` was edited since it was last applied. Use `--force` to back it up and restore it. Skipping...",
+                path
+            );
+            failed = true;
+            continue;
+        }
+
+        match transaction.copy(&backup_path(&backup_dir, &path), &path, drifted) {
+            Ok(_) => println!("INFO: Restored `{:#?}`.", path),
+            Err(e) => {
+                eprintln!(
+                    "ERROR: Failed to restore `{:#?}`, because of the following error:\n{}",
+                    path, e
+                );
+                failed = true;
+                continue;
+            }
+        }
+
+        restored_paths.push(path);
+    }
+
+    transaction.commit(&mut backup);
+
+    for path in restored_paths {
+        if let Err(e) = remove_file(backup_path(&backup_dir, &path)) {
+            println!(
+                "WARNING: Failed to remove the restored `{:#?}` from the backup, because of the following error:\n{}",
+                path, e
+            );
+        }
+
+        // The restored file is not managed by DotMan anymore
+        state.remove(&path);
+    }
+
+    state.write();
+    // Drops the migrated `applied_paths`
+    if migrated {
+        write_config(&config_struct);
+    }
+
+    prune_backup(&backup_dir);
+
+    if failed {
+        exit(1);
+    }
+}
@@ -1,4 +1,39 @@
-use clap::{builder::ValueParser, Arg, Command, ValueHint};
+use clap::{builder::ValueParser, Arg, ArgAction, Command, ValueHint};
+
+/// The `paths` and `--tag` arguments that select the managed files a command works on.
+fn selection_args(action: &str) -> [Arg; 2] {
+    [
+        Arg::new("paths")
+            .help(format!(
+                "Sources or destinations of the files to {}, as paths or glob patterns. Defaults to all managed files",
+                action
+            ))
+            .index(1)
+            .num_args(1..)
+            .value_hint(ValueHint::AnyPath)
+            .value_parser(ValueParser::path_buf()),
+        Arg::new("tag")
+            .long("tag")
+            .help("Only select files with this tag. Can be given multiple times")
+            .action(ArgAction::Append)
+            .value_hint(ValueHint::Other),
+    ]
+}
+
+fn on_conflict_arg(default: &'static str) -> Arg {
+    Arg::new("on_conflict")
+        .long("on-conflict")
+        .help("How destinations edited since the last apply are handled if their source changed as well. `ask` skips them without a terminal")
+        .value_parser(["ask", "keep-local", "take-source", "skip"])
+        .default_value(default)
+}
+
+fn no_hooks_arg() -> Arg {
+    Arg::new("no_hooks")
+        .long("no-hooks")
+        .help("Do not run the configured pre-apply, post-apply and on-change hooks")
+        .action(ArgAction::SetTrue)
+}
 
 pub fn cli() -> Command {
     Command::new("dotman")
@@ -14,7 +49,7 @@ pub fn cli() -> Command {
                 .allow_external_subcommands(true)
                 .subcommand(
                     Command::new("add")
-                        .about("Adds selected file or directory to your dotfile storage")
+                        .about("Adds selected file or directory to your dotfile storage. Directories are deployed as a whole, including files added to them later")
                         .arg_required_else_help(true)
                         .arg(
                             Arg::new("path")
@@ -22,6 +57,19 @@ pub fn cli() -> Command {
                                 .index(1)
                                 .value_hint(ValueHint::AnyPath)
                                 .value_parser(ValueParser::path_buf()),
+                        )
+                        .arg(
+                            Arg::new("flatten")
+                                .long("flatten")
+                                .help("Add every file of the directory separately instead of the directory itself")
+                                .action(ArgAction::SetTrue),
+                        )
+                        .arg(
+                            Arg::new("tag")
+                                .long("tag")
+                                .help("Tag the added files to select them with `--tag` later. Can be given multiple times")
+                                .action(ArgAction::Append)
+                                .value_hint(ValueHint::Other),
                         ),
                 )
                 .subcommand(
@@ -53,10 +101,18 @@ pub fn cli() -> Command {
                         )
                         .arg(
                             Arg::new("destination_path")
+                                .help("Where the file is deployed. Can contain `~`, `$HOME`, `$XDG_CONFIG_HOME` and template variables like `$hostname`, which are expanded on apply")
                                 .required(true)
                                 .index(2)
                                 .value_hint(ValueHint::AnyPath)
                                 .value_parser(ValueParser::path_buf()),
+                        )
+                        .arg(
+                            Arg::new("strategy")
+                                .long("strategy")
+                                .help("How the file is deployed. Links are never rendered as templates")
+                                .value_parser(["copy", "symlink", "hardlink"])
+                                .default_value("copy"),
                         ),
                 )
                 .subcommand(
@@ -130,6 +186,115 @@ pub fn cli() -> Command {
                         .about("Pulls remote changes and applies them to local repository."),
                 ),
         )
+        .subcommand(
+            Command::new("template")
+                .about("Work with your source and template files")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommand(
+                    Command::new("fmt")
+                        .about("Formats source and template files. Defaults to all managed files")
+                        .arg(
+                            Arg::new("check")
+                                .long("check")
+                                .help("Only report unformatted files and fail if there are any")
+                                .action(ArgAction::SetTrue),
+                        )
+                        .arg(
+                            Arg::new("paths")
+                                .index(1)
+                                .num_args(1..)
+                                .value_hint(ValueHint::AnyPath)
+                                .value_parser(ValueParser::path_buf()),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("apply")
+                .about("Renders your templates and writes them to their link destinations")
+                .arg(
+                    Arg::new("force")
+                        .long("force")
+                        .help("Back up and replace destinations that were not created by DotMan")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("dry_run")
+                        .long("dry-run")
+                        .help("Show the changes as diffs without writing anything. Exits with code 2 if changes are pending")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(on_conflict_arg("ask"))
+                .arg(no_hooks_arg())
+                .args(selection_args("apply")),
+        )
+        .subcommand(
+            Command::new("unapply")
+                .about("Removes deployed files that were not edited since they were applied and restores their backups")
+                .args(selection_args("remove")),
+        )
+        .subcommand(
+            Command::new("sync-back")
+                .about("Copies deployed files edited since they were applied back to their sources. Changes of templates are merged in your editor")
+                .args(selection_args("sync")),
+        )
+        .subcommand(
+            Command::new("watch")
+                .about("Applies the templates affected by a change of your sources, source files or data files until it is stopped")
+                .arg(
+                    Arg::new("debounce")
+                        .long("debounce")
+                        .help("Milliseconds to wait for further changes before applying")
+                        .value_parser(clap::value_parser!(u64))
+                        .default_value("300"),
+                )
+                .arg(on_conflict_arg("skip"))
+                .arg(no_hooks_arg()),
+        )
+        .subcommand(
+            Command::new("status")
+                .about("Shows the state of every managed file and the git status of the dotman home")
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .help("Print the status as JSON")
+                        .action(ArgAction::SetTrue),
+                )
+                .args(selection_args("show")),
+        )
+        .subcommand(
+            Command::new("backup")
+                .about("Inspect and restore the files replaced by `apply --force`")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommand(Command::new("list").about("Lists all backups and their files"))
+                .subcommand(
+                    Command::new("restore")
+                        .about("Moves the files of a backup back to their original location")
+                        .arg_required_else_help(true)
+                        .arg(
+                            Arg::new("id")
+                                .required(true)
+                                .index(1)
+                                .value_hint(ValueHint::Other)
+                                .value_parser(ValueParser::string()),
+                        )
+                        .arg(
+                            Arg::new("paths")
+                                .help("Only restore these files")
+                                .index(2)
+                                .num_args(1..)
+                                .value_hint(ValueHint::AnyPath)
+                                .value_parser(ValueParser::path_buf()),
+                        )
+                        .arg(
+                            Arg::new("force")
+                                .long("force")
+                                .help("Back up and replace deployed files that were edited since they were applied")
+                                .action(ArgAction::SetTrue),
+                        ),
+                ),
+        )
         .subcommand(
             Command::new("set_home")
                 .about("Sets the location for dotfiles to be stored e.g. a local git repo")
@@ -1,6 +1,13 @@
-use std::path::PathBuf;
+use std::{
+    ffi::OsStr,
+    path::{absolute, Path, PathBuf},
+    str::FromStr,
+};
 
 use serde::{Deserialize, Serialize};
+use template_engine::{config::DataFile, paths::expand_path, TemplateEngine};
+
+use crate::{hooks::HookConfig, render::variables_engine, utils::walk_files};
 
 #[derive(Serialize, Deserialize)]
 pub struct ConfigFile {
@@ -8,7 +15,20 @@ pub struct ConfigFile {
     pub managed_paths: Vec<LinkedFile>,
     pub remote_url: Option<String>,
     pub git_init: bool,
+    /// Deprecated: Deployed destinations are tracked in the state file, which takes over these paths on the next apply
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub applied_paths: Vec<PathBuf>,
+    #[serde(default)]
+    pub data_files: Vec<DataFile>,
+    /// Activates the `profile` blocks of this profile, e.g. `work` or `personal`
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Command that merges conflicting destinations, e.g. `meld $LOCAL $BASE $REMOTE -o $MERGED`.
+    /// Conflicts are merged in `$EDITOR` if it is not set
+    #[serde(default)]
+    pub merge_tool: Option<String>,
+    #[serde(default, skip_serializing_if = "HookConfig::is_empty")]
+    pub hooks: HookConfig,
 }
 
 impl Default for ConfigFile {
@@ -24,22 +44,95 @@ impl Default for ConfigFile {
             remote_url: None,
             git_init: false,
             applied_paths: Vec::new(),
+            data_files: Vec::new(),
+            profile: None,
+            merge_tool: None,
+            hooks: HookConfig::default(),
         }
     }
 }
 
 impl ConfigFile {
-    pub fn paths_iter(&self) -> impl Iterator<Item = (PathBuf, Option<PathBuf>)> + '_ {
+    pub fn data_files_iter(&self) -> impl Iterator<Item = (Option<String>, PathBuf)> + '_ {
+        self.data_files
+            .iter()
+            .map(|x| (x.variable_name(), x.full_path(&self.home_path)))
+    }
+
+    /// The managed files with their paths expanded and every managed directory replaced by the files it contains.
+    /// Files whose paths can not be expanded are skipped with a warning.
+    pub fn linked_files(&self) -> Vec<LinkedFile> {
+        // Only evaluated once a path uses a template variable
+        let mut template_engine: Option<Option<TemplateEngine>> = None;
+
+        let mut linked_files = Vec::new();
+
+        for linked_file in &self.managed_paths {
+            // Sources without a destination define the variables, so their paths can not use them
+            let expanded = match linked_file.expand_paths(None) {
+                Err(_) if linked_file.destination.is_some() => {
+                    let template_engine =
+                        template_engine.get_or_insert_with(|| variables_engine(self).ok());
+                    linked_file.expand_paths(template_engine.as_ref())
+                }
+                result => result,
+            };
+
+            match expanded {
+                Ok(x) => linked_files.extend(x.expand(&self.home_path)),
+                Err(e) => println!(
+                    "WARNING: Failed to expand the paths of `{:#?}`: {}. Skipping...",
+                    linked_file.source, e
+                ),
+            }
+        }
+
+        linked_files
+    }
+
+    /// The `.te` files managed without a destination, which define the variables of the templates.
+    pub fn variable_sources(&self) -> Vec<PathBuf> {
         self.managed_paths
             .iter()
-            .map(|x| (x.source.clone(), x.destination.clone()))
+            .filter(|x| x.destination.is_none())
+            .filter_map(|x| x.expand_paths(None).ok())
+            .flat_map(|x| x.expand(&self.home_path))
+            .filter(LinkedFile::is_variable_source)
+            .map(|x| x.source)
+            .collect()
+    }
+
+    pub fn paths_iter(&self) -> impl Iterator<Item = (PathBuf, Option<PathBuf>)> + '_ {
+        self.linked_files()
+            .into_iter()
+            .map(|x| (x.source, x.destination))
     }
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Eq, Hash)]
+/// A managed file or directory. The files of a directory are deployed below its destination, mirroring the tree.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone)]
 pub struct LinkedFile {
     pub source: PathBuf,
     pub destination: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "DeployStrategy::is_copy")]
+    pub strategy: DeployStrategy,
+    /// Permission bits of the copied destination. Defaults to the permission bits of the source
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<FileMode>,
+    /// Owner of the copied destination as `user` or `user:group`, by name or id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Names like `shell` or `wm` to select groups of files with `--tag`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Only deploys the file if this comparison holds, e.g. `$hostname == "desktop"` or `$os != "macos"`.
+    /// It is written like the condition of an `if` expression and can use every template variable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<String>,
+    /// Commands run after `apply` changed the file, like `systemctl --user restart waybar`.
+    /// Each command runs once per apply, even if several files of a directory changed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub on_change: Vec<String>,
 }
 
 impl LinkedFile {
@@ -53,6 +146,115 @@ impl LinkedFile {
         Self {
             source,
             destination,
+            strategy: DeployStrategy::default(),
+            mode: None,
+            owner: None,
+            tags: Vec::new(),
+            condition: None,
+            on_change: Vec::new(),
+        }
+    }
+
+    /// The file with `~`, `$HOME`, `$XDG_CONFIG_HOME` and template variables in its paths expanded.
+    pub fn expand_paths(
+        &self,
+        template_engine: Option<&TemplateEngine>,
+    ) -> Result<LinkedFile, String> {
+        let destination = match &self.destination {
+            Some(x) => Some(expand_path(x, template_engine)?),
+            None => None,
+        };
+
+        Ok(LinkedFile {
+            source: expand_path(&self.source, template_engine)?,
+            destination,
+            ..self.clone()
+        })
+    }
+
+    /// Sources without a destination define the variables of the templates, if they are `.te` files.
+    pub fn is_variable_source(&self) -> bool {
+        self.destination.is_none() && self.source.extension() == Some(OsStr::new("te"))
+    }
+
+    /// Whether the source is the given path, which may be written differently than in the config.
+    pub fn has_source(&self, path: &Path) -> bool {
+        let expand = |x: &Path| expand_path(x, None).unwrap_or_else(|_| x.to_path_buf());
+        let (source, path) = (expand(&self.source), expand(path));
+
+        source == path || absolute(source).is_ok_and(|x| absolute(path).is_ok_and(|y| x == y))
+    }
+
+    /// The files currently inside a managed directory that are not ignored, which share its settings.
+    /// Files are returned as they are.
+    pub fn expand(&self, home_path: &Path) -> Vec<LinkedFile> {
+        if !self.source.is_dir() {
+            return vec![self.clone()];
+        }
+
+        walk_files(&self.source, home_path)
+            .into_iter()
+            .filter_map(|path| {
+                let relative_path = path.strip_prefix(&self.source).ok()?;
+
+                Some(LinkedFile {
+                    source: path.clone(),
+                    destination: self.destination.as_ref().map(|x| x.join(relative_path)),
+                    ..self.clone()
+                })
+            })
+            .collect()
+    }
+}
+
+/// Unix permission bits, written as an octal string like `"0600"` in the config.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Copy, Debug)]
+#[serde(try_from = "String", into = "String")]
+pub struct FileMode(pub u32);
+
+impl TryFrom<String> for FileMode {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match u32::from_str_radix(&value, 8) {
+            Ok(mode) if mode <= 0o7777 => Ok(FileMode(mode)),
+            _ => Err(format!("Invalid file mode `{}`", value)),
+        }
+    }
+}
+
+impl From<FileMode> for String {
+    fn from(value: FileMode) -> Self {
+        format!("{:04o}", value.0)
+    }
+}
+
+/// How `apply` deploys a source file to its destination.
+/// Only copies are rendered by the template engine, links always point to the unmodified source.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Copy, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum DeployStrategy {
+    #[default]
+    Copy,
+    Symlink,
+    Hardlink,
+}
+
+impl DeployStrategy {
+    pub fn is_copy(&self) -> bool {
+        *self == DeployStrategy::Copy
+    }
+}
+
+impl FromStr for DeployStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "copy" => Ok(DeployStrategy::Copy),
+            "symlink" => Ok(DeployStrategy::Symlink),
+            "hardlink" => Ok(DeployStrategy::Hardlink),
+            _ => Err(format!("Unknown deploy strategy `{}`", s)),
         }
     }
 }
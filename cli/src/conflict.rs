@@ -0,0 +1,214 @@
+use std::{
+    env::temp_dir,
+    fs::{create_dir_all, read_to_string, remove_dir_all, write},
+    io::{stdin, stdout, IsTerminal, Write},
+    path::{Path, PathBuf},
+    process::{self, Command},
+    str::FromStr,
+};
+
+use crate::{diff::print_diff, utils::open_editor};
+
+/// How `apply` resolves destinations that were edited locally while their source changed as well.
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub enum ConflictPolicy {
+    /// Prompts for every conflicting file, or skips it without a terminal
+    #[default]
+    Ask,
+    KeepLocal,
+    TakeSource,
+    Skip,
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ask" => Ok(ConflictPolicy::Ask),
+            "keep-local" => Ok(ConflictPolicy::KeepLocal),
+            "take-source" => Ok(ConflictPolicy::TakeSource),
+            "skip" => Ok(ConflictPolicy::Skip),
+            _ => Err(format!("Unknown conflict policy `{}`", s)),
+        }
+    }
+}
+
+pub enum Resolution {
+    /// Keeps the destination as it is and does not offer the current source version again
+    KeepLocal,
+    /// Replaces the destination with the rendered source
+    TakeSource,
+    /// Writes the given content, which combines both versions
+    Merged(String),
+    Skip,
+}
+
+/// The three versions of a conflicting destination.
+pub struct Conflict<'a> {
+    pub destination: &'a Path,
+    pub local: &'a str,
+    /// What the last apply wrote, if it is still known
+    pub base: Option<String>,
+    pub rendered: &'a str,
+}
+
+impl Conflict<'_> {
+    pub fn resolve(&self, policy: ConflictPolicy, merge_tool: Option<&str>) -> Resolution {
+        match policy {
+            ConflictPolicy::KeepLocal => return Resolution::KeepLocal,
+            ConflictPolicy::TakeSource => return Resolution::TakeSource,
+            ConflictPolicy::Skip => return Resolution::Skip,
+            ConflictPolicy::Ask => (),
+        }
+
+        if !stdin().is_terminal() {
+            return Resolution::Skip;
+        }
+
+        println!(
+            "INFO: `{:#?}` was edited since it was last applied and its source changed as well.",
+            self.destination
+        );
+
+        loop {
+            print!("[k]eep local, [t]ake source, view [d]iff, [m]erge, [s]kip: ");
+            let _ = stdout().flush();
+
+            let mut answer = String::new();
+            match stdin().read_line(&mut answer) {
+                Ok(0) | Err(_) => return Resolution::Skip,
+                Ok(_) => (),
+            }
+
+            match answer.trim().to_lowercase().as_str() {
+                "k" | "keep" => return Resolution::KeepLocal,
+                "t" | "take" => return Resolution::TakeSource,
+                "s" | "skip" => return Resolution::Skip,
+                "d" | "diff" => print_diff(
+                    self.local,
+                    self.rendered,
+                    &self.destination.to_string_lossy(),
+                    "source",
+                ),
+                "m" | "merge" => {
+                    if let Some(merged) = self.merge(merge_tool) {
+                        return Resolution::Merged(merged);
+                    }
+                }
+                answer => println!("WARNING: Unknown answer `{}`.", answer),
+            }
+        }
+    }
+
+    /// Lets the user combine both versions in the merge tool or `$EDITOR`.
+    /// Returns `None` if the merge was aborted or conflict markers are left.
+    fn merge(&self, merge_tool: Option<&str>) -> Option<String> {
+        let merge_dir = temp_dir().join(format!("dotman-merge-{}", process::id()));
+
+        let merged = self.merge_in(&merge_dir, merge_tool);
+        let _ = remove_dir_all(&merge_dir);
+
+        match merged {
+            Ok(merged) if merged.lines().any(|x| x.starts_with("<<<<<<<")) => {
+                println!("WARNING: The merged file still contains conflict markers.");
+                None
+            }
+            Ok(merged) => Some(merged),
+            Err(e) => {
+                eprintln!(
+                    "ERROR: Failed to merge, because of the following error:\n{}",
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    fn merge_in(&self, merge_dir: &Path, merge_tool: Option<&str>) -> std::io::Result<String> {
+        create_dir_all(merge_dir)?;
+
+        let file_name = self
+            .destination
+            .file_name()
+            .map(|x| x.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let version_path =
+            |version: &str| -> PathBuf { merge_dir.join(format!("{}.{}", file_name, version)) };
+
+        let local_path = version_path("LOCAL");
+        let base_path = version_path("BASE");
+        let remote_path = version_path("REMOTE");
+        let merged_path = merge_dir.join(&file_name);
+
+        write(&local_path, self.local)?;
+        write(&base_path, self.base.as_deref().unwrap_or_default())?;
+        write(&remote_path, self.rendered)?;
+        write(
+            &merged_path,
+            self.conflict_markers(&local_path, &base_path, &remote_path),
+        )?;
+
+        match merge_tool {
+            // The tool is run like a git mergetool command, with the paths in environment variables
+            Some(merge_tool) => {
+                let status = Command::new("sh")
+                    .arg("-c")
+                    .arg(merge_tool)
+                    .env("LOCAL", &local_path)
+                    .env("BASE", &base_path)
+                    .env("REMOTE", &remote_path)
+                    .env("MERGED", &merged_path)
+                    .status()?;
+
+                if !status.success() {
+                    return Err(std::io::Error::other(format!(
+                        "The merge tool exited with {}",
+                        status
+                    )));
+                }
+            }
+            None => {
+                if !open_editor(&merged_path) {
+                    return Err(std::io::Error::other("The editor exited with an error"));
+                }
+            }
+        }
+
+        read_to_string(&merged_path)
+    }
+
+    /// Both versions with conflict markers around the lines changed on both sides.
+    /// Falls back to a single conflict around the whole file if `git merge-file` is not available.
+    fn conflict_markers(&self, local_path: &Path, base_path: &Path, remote_path: &Path) -> String {
+        let output = Command::new("git")
+            .arg("merge-file")
+            .arg("-p")
+            .args(["-L", "local", "-L", "base", "-L", "source"])
+            .arg(local_path)
+            .arg(base_path)
+            .arg(remote_path)
+            .output();
+
+        // The exit code is the number of conflicts, negative codes are errors
+        match output {
+            Ok(output) if output.status.code().is_some_and(|x| (0..=127).contains(&x)) => {
+                String::from_utf8_lossy(&output.stdout).to_string()
+            }
+            _ => format!(
+                "<<<<<<< local\n{}=======\n{}>>>>>>> source\n",
+                with_newline(self.local),
+                with_newline(self.rendered)
+            ),
+        }
+    }
+}
+
+fn with_newline(content: &str) -> String {
+    if content.is_empty() || content.ends_with('\n') {
+        content.to_string()
+    } else {
+        format!("{}\n", content)
+    }
+}
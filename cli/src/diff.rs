@@ -0,0 +1,39 @@
+use std::io::{stdout, IsTerminal};
+
+use colored::Colorize;
+use similar::{ChangeTag, TextDiff};
+
+/// Prints a unified diff from `old` to `new`, colored if stdout is a terminal.
+pub fn print_diff(old: &str, new: &str, old_label: &str, new_label: &str) {
+    if !stdout().is_terminal() {
+        colored::control::set_override(false);
+    }
+
+    let diff = TextDiff::from_lines(old, new);
+    let unified_diff = diff.unified_diff().header(old_label, new_label).to_string();
+
+    for line in unified_diff.lines() {
+        let line = if line.starts_with("---") || line.starts_with("+++") {
+            line.bold()
+        } else if line.starts_with("@@") {
+            line.cyan()
+        } else if line.starts_with('+') {
+            line.green()
+        } else if line.starts_with('-') {
+            line.red()
+        } else {
+            line.normal()
+        };
+
+        println!("{}", line);
+    }
+}
+
+/// The lines removed and added from `old` to `new` in order, without their position.
+pub fn changed_lines(old: &str, new: &str) -> Vec<(ChangeTag, String)> {
+    TextDiff::from_lines(old, new)
+        .iter_all_changes()
+        .filter(|x| x.tag() != ChangeTag::Equal)
+        .map(|x| (x.tag(), x.value().to_string()))
+        .collect()
+}
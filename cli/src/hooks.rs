@@ -0,0 +1,146 @@
+use std::{
+    env::temp_dir,
+    fs::{read, remove_file, File},
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use nix::{
+    sys::signal::{killpg, Signal},
+    unistd::Pid,
+};
+use serde::{Deserialize, Serialize};
+
+/// Commands `apply` runs around deploying the managed files.
+#[derive(Serialize, Deserialize, Default)]
+pub struct HookConfig {
+    /// Run before any file is written. `apply` stops if one of them fails
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_apply: Vec<String>,
+    /// Run after the files were written
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_apply: Vec<String>,
+    /// Seconds after which a hook is killed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+/// Timeout of hooks without a configured one.
+const DEFAULT_TIMEOUT: u64 = 60;
+
+impl HookConfig {
+    pub fn is_empty(&self) -> bool {
+        self.pre_apply.is_empty() && self.post_apply.is_empty() && self.timeout.is_none()
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout.unwrap_or(DEFAULT_TIMEOUT))
+    }
+}
+
+/// A new file for the output of a hook.
+fn output_path() -> PathBuf {
+    static HOOK_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    temp_dir().join(format!(
+        "dotman-hook-{}-{}",
+        process::id(),
+        HOOK_COUNT.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Runs the commands one after another and returns whether all of them succeeded.
+pub fn run_hooks(commands: &[String], working_dir: &Path, timeout: Duration) -> bool {
+    let mut success = true;
+
+    for command in commands {
+        success &= run_hook(command, working_dir, timeout);
+    }
+
+    success
+}
+
+/// Runs a command with `sh -c` in `working_dir` and prints its captured output.
+/// The command is killed if it does not finish within the timeout.
+pub fn run_hook(command: &str, working_dir: &Path, timeout: Duration) -> bool {
+    println!("INFO: Running hook `{}`...", command);
+
+    // Background processes started by the hook keep its output open after it exits,
+    // so the output goes to a file instead of pipes, which could only be read to the end once they exited as well
+    let output_path = output_path();
+    let _ = remove_file(&output_path);
+    let output_file = match File::create_new(&output_path) {
+        Ok(x) => x,
+        Err(e) => {
+            println!(
+                "WARNING: Failed to create the output file of hook `{}`, because of the following error:\n{}",
+                command, e
+            );
+            return false;
+        }
+    };
+
+    let mut child = match output_file.try_clone().and_then(|stderr| {
+        Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .current_dir(working_dir)
+            .stdin(Stdio::null())
+            .stdout(output_file)
+            .stderr(stderr)
+            // Its own process group, so a timeout also kills the processes started by the hook
+            .process_group(0)
+            .spawn()
+    }) {
+        Ok(x) => x,
+        Err(e) => {
+            let _ = remove_file(&output_path);
+            println!(
+                "WARNING: Failed to run hook `{}`, because of the following error:\n{}",
+                command, e
+            );
+            return false;
+        }
+    };
+
+    let deadline = Instant::now() + timeout;
+
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Some(status),
+            Ok(None) if Instant::now() < deadline => sleep(Duration::from_millis(10)),
+            _ => {
+                let _ = killpg(Pid::from_raw(child.id() as i32), Signal::SIGKILL);
+                let _ = child.wait();
+                break None;
+            }
+        }
+    };
+
+    let output = read(&output_path).unwrap_or_default();
+    let _ = remove_file(&output_path);
+
+    for line in String::from_utf8_lossy(&output).lines() {
+        println!("    {}", line);
+    }
+
+    match status {
+        Some(status) if status.success() => true,
+        Some(status) => {
+            println!("WARNING: Hook `{}` failed with {}.", command, status);
+            false
+        }
+        None => {
+            println!(
+                "WARNING: Hook `{}` did not finish within {} seconds and was killed.",
+                command,
+                timeout.as_secs()
+            );
+            false
+        }
+    }
+}
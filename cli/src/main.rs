@@ -3,18 +3,41 @@ use clap::ArgMatches;
 use std::{fs::File, io::Write, path::PathBuf, process::exit};
 
 mod apply;
+mod backup;
 mod cli;
 mod config;
+mod conflict;
+mod diff;
 mod git;
+mod hooks;
+mod permissions;
+mod render;
+mod selection;
 mod source;
+mod state;
+mod status;
+mod sync_back;
+mod template;
+mod transaction;
+mod unapply;
 mod utils;
+mod watch;
+
+#[cfg(test)]
+mod tests;
 
 use apply::apply;
+use backup::backup;
 use cli::cli;
 use config::ConfigFile;
 use git::git;
 use source::source;
+use status::status;
+use sync_back::sync_back;
+use template::template;
+use unapply::unapply;
 use utils::*;
+use watch::watch;
 
 fn main() {
     let config_file_path = config_file_path();
@@ -44,7 +67,13 @@ fn main() {
         Some(("set_home", sub_matches)) => set_home(sub_matches),
         Some(("source", sub_matches)) => source(sub_matches),
         Some(("git", sub_matches)) => git(sub_matches),
-        Some(("apply", _submatches)) => apply(),
+        Some(("template", sub_matches)) => template(sub_matches),
+        Some(("apply", sub_matches)) => apply(sub_matches),
+        Some(("backup", sub_matches)) => backup(sub_matches),
+        Some(("status", sub_matches)) => status(sub_matches),
+        Some(("unapply", sub_matches)) => unapply(sub_matches),
+        Some(("sync-back", sub_matches)) => sync_back(sub_matches),
+        Some(("watch", sub_matches)) => watch(sub_matches),
         Some((subcommand, _)) => {
             eprintln!("Unknown subcommand {}", subcommand);
             exit(1);
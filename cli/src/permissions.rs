@@ -0,0 +1,104 @@
+use std::{
+    fs::{set_permissions, Permissions},
+    io,
+    os::unix::fs::{chown, MetadataExt, PermissionsExt},
+    path::Path,
+    str::FromStr,
+};
+
+use nix::unistd::{Group, User};
+
+use crate::config::LinkedFile;
+
+/// Permission bits and owner a copied destination is deployed with.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FileAttributes {
+    pub mode: u32,
+    pub owner: Option<Owner>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Owner {
+    pub uid: u32,
+    pub gid: Option<u32>,
+}
+
+impl FromStr for Owner {
+    type Err = String;
+
+    /// Parses `user` or `user:group`. Names are looked up in the user database, numbers are used as ids.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (user, group) = match s.split_once(':') {
+            Some((user, group)) => (user, Some(group)),
+            None => (s, None),
+        };
+
+        let uid = match user.parse::<u32>() {
+            Ok(uid) => uid,
+            Err(_) => match User::from_name(user) {
+                Ok(Some(user)) => user.uid.as_raw(),
+                _ => return Err(format!("Unknown user `{}`", user)),
+            },
+        };
+
+        let gid = match group {
+            Some(group) => match group.parse::<u32>() {
+                Ok(gid) => Some(gid),
+                Err(_) => match Group::from_name(group) {
+                    Ok(Some(group)) => Some(group.gid.as_raw()),
+                    _ => return Err(format!("Unknown group `{}`", group)),
+                },
+            },
+            None => None,
+        };
+
+        Ok(Owner { uid, gid })
+    }
+}
+
+impl FileAttributes {
+    /// The attributes declared by the managed file, with the permission bits of its source as the default mode.
+    pub fn of(linked_file: &LinkedFile) -> Result<Self, String> {
+        let mode = match linked_file.mode {
+            Some(mode) => mode.0,
+            None => {
+                linked_file
+                    .source
+                    .metadata()
+                    .map_err(|e| e.to_string())?
+                    .mode()
+                    & 0o7777
+            }
+        };
+
+        let owner = linked_file
+            .owner
+            .as_deref()
+            .map(str::parse::<Owner>)
+            .transpose()?;
+
+        Ok(Self { mode, owner })
+    }
+
+    /// Applies the attributes to `path`. The owner is changed first, because it can reset setuid bits.
+    pub fn set(&self, path: &Path) -> io::Result<()> {
+        if let Some(owner) = self.owner {
+            chown(path, Some(owner.uid), owner.gid)?;
+        }
+
+        set_permissions(path, Permissions::from_mode(self.mode))
+    }
+
+    /// Whether `path` already has these attributes.
+    pub fn matches(&self, path: &Path) -> bool {
+        let Ok(metadata) = path.symlink_metadata() else {
+            return false;
+        };
+
+        let owner_matches = self.owner.is_none_or(|owner| {
+            metadata.uid() == owner.uid && owner.gid.is_none_or(|gid| metadata.gid() == gid)
+        });
+
+        metadata.mode() & 0o7777 == self.mode && owner_matches
+    }
+}
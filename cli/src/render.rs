@@ -0,0 +1,89 @@
+use std::{fs::read_to_string, process::exit};
+
+use template_engine::{analysis::variable_references, TemplateEngine};
+
+use crate::{
+    config::{ConfigFile, LinkedFile},
+    state::hash,
+};
+
+/// A template engine with the hostname, profile and data files of the config.
+pub fn template_engine(config_struct: &ConfigFile) -> TemplateEngine {
+    let mut template_engine = TemplateEngine::for_host(config_struct.profile.as_deref());
+
+    // Load data files into the template engine
+    for data_file in &config_struct.data_files {
+        if let Err(e) = template_engine.set_data_file(data_file, &config_struct.home_path) {
+            eprintln!(
+                "ERROR: Failed to load data file with path `{:#?}`, because of the following error:\n{}",
+                data_file.full_path(&config_struct.home_path),
+                e
+            );
+            exit(1);
+        }
+    }
+
+    template_engine
+}
+
+/// The contents of all managed source files, which define the variables of the templates.
+fn source_files(config_struct: &ConfigFile) -> Vec<(String, bool)> {
+    config_struct
+        .variable_sources()
+        .into_iter()
+        .filter_map(|x| read_to_string(x).ok())
+        .map(|x| (x, true))
+        .collect()
+}
+
+/// A template engine holding the variables of all managed source files.
+pub fn variables_engine(config_struct: &ConfigFile) -> anyhow::Result<TemplateEngine> {
+    let mut template_engine = template_engine(config_struct);
+
+    template_engine.evaluate_files(source_files(config_struct))?;
+
+    Ok(template_engine)
+}
+
+/// A hash of the values of the variables a template uses, which changes whenever its output may change.
+pub fn variables_hash(template_engine: &TemplateEngine, content: &str) -> String {
+    let mut identifiers = variable_references(content, false)
+        .into_iter()
+        .filter(|x| !x.assignment)
+        .map(|x| x.identifier)
+        .collect::<Vec<String>>();
+
+    // `host` and `profile` blocks depend on them without referencing them
+    identifiers.extend(["hostname".to_string(), "profile".to_string()]);
+    identifiers.sort();
+    identifiers.dedup();
+
+    let values = identifiers
+        .iter()
+        .map(|x| format!("{}={:?}\n", x, template_engine.variables().get(x)))
+        .collect::<String>();
+
+    hash(values.as_bytes())
+}
+
+/// Whether the condition of a managed file holds on this machine. Files without a condition are always deployed.
+pub fn condition_holds(
+    linked_file: &LinkedFile,
+    variables_engine: &TemplateEngine,
+) -> anyhow::Result<bool> {
+    match &linked_file.condition {
+        Some(condition) => variables_engine.evaluate_condition(condition),
+        None => Ok(true),
+    }
+}
+
+/// Renders a single template the way `apply` does, after evaluating all managed source files.
+pub fn render_template(config_struct: &ConfigFile, content: String) -> anyhow::Result<String> {
+    let mut template_engine = template_engine(config_struct);
+
+    let mut input = source_files(config_struct);
+    input.push((content, false));
+
+    // Safe unwrap: One result is returned per input file
+    Ok(template_engine.evaluate_files(input)?.pop().unwrap())
+}
@@ -0,0 +1,110 @@
+use std::{
+    path::{absolute, PathBuf},
+    process::exit,
+};
+
+use clap::ArgMatches;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+use crate::config::LinkedFile;
+
+/// The managed files chosen by the `paths` and `tag` arguments of a subcommand.
+/// A file is selected if it matches any of the paths and carries any of the tags. Missing arguments select every file.
+pub struct Selection {
+    /// Selects files by their path or a directory containing them
+    paths: Vec<PathBuf>,
+    /// Patterns without a `/`, which are matched against file names
+    name_patterns: GlobSet,
+    /// Patterns with a `/`, which are matched against absolute paths
+    path_patterns: GlobSet,
+    tags: Vec<String>,
+}
+
+fn is_pattern(path: &str) -> bool {
+    path.contains(['*', '?', '[', '{'])
+}
+
+impl Selection {
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        let mut paths = Vec::new();
+        let mut name_patterns = GlobSetBuilder::new();
+        let mut path_patterns = GlobSetBuilder::new();
+
+        for path in matches.get_many::<PathBuf>("paths").into_iter().flatten() {
+            let Ok(absolute_path) = absolute(path) else {
+                continue;
+            };
+
+            let path = path.to_string_lossy();
+
+            if !is_pattern(&path) {
+                paths.push(absolute_path);
+                continue;
+            }
+
+            let (patterns, pattern) = if path.contains('/') {
+                (
+                    &mut path_patterns,
+                    absolute_path.to_string_lossy().to_string(),
+                )
+            } else {
+                (&mut name_patterns, path.to_string())
+            };
+
+            match GlobBuilder::new(&pattern).literal_separator(true).build() {
+                Ok(glob) => {
+                    patterns.add(glob);
+                }
+                Err(e) => {
+                    eprintln!("ERROR: Invalid glob pattern `{}`:\n{}", pattern, e);
+                    exit(1);
+                }
+            }
+        }
+
+        let tags = matches
+            .get_many::<String>("tag")
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect();
+
+        // Safe unwraps: Every glob was built successfully
+        Self {
+            paths,
+            name_patterns: name_patterns.build().unwrap(),
+            path_patterns: path_patterns.build().unwrap(),
+            tags,
+        }
+    }
+
+    fn has_paths(&self) -> bool {
+        !self.paths.is_empty() || !self.name_patterns.is_empty() || !self.path_patterns.is_empty()
+    }
+
+    /// Whether the file is selected by its source or destination and its tags.
+    pub fn matches(&self, linked_file: &LinkedFile) -> bool {
+        let tag_matches =
+            self.tags.is_empty() || linked_file.tags.iter().any(|x| self.tags.contains(x));
+
+        if !tag_matches {
+            return false;
+        }
+
+        if !self.has_paths() {
+            return true;
+        }
+
+        [Some(&linked_file.source), linked_file.destination.as_ref()]
+            .into_iter()
+            .flatten()
+            .filter_map(|x| absolute(x).ok())
+            .any(|path| {
+                self.paths.iter().any(|x| path.starts_with(x))
+                    || self.path_patterns.is_match(&path)
+                    || path
+                        .file_name()
+                        .is_some_and(|x| self.name_patterns.is_match(x))
+            })
+    }
+}
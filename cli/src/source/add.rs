@@ -1,11 +1,16 @@
 use clap::ArgMatches;
 
-use std::{path::PathBuf, process::exit};
+use std::{
+    fs::read,
+    path::{absolute, PathBuf},
+    process::exit,
+};
+use template_engine::paths::portable_path;
 
 use crate::{
     config::LinkedFile,
     flat_file_array, get_config_file_content,
-    utils::{has_unique_elements, write_config},
+    utils::{as_text, write_config},
 };
 
 pub fn add(sub_matches: &ArgMatches) {
@@ -20,22 +25,57 @@ pub fn add(sub_matches: &ArgMatches) {
 
     let mut config_struct = get_config_file_content();
 
-    if path.is_dir() {
-        let files = flat_file_array(path.to_path_buf());
-        for file in files {
-            config_struct
-                .managed_paths
-                .push(LinkedFile::new(file, None));
-        }
+    // Directories are managed as a whole and pick up files added to them later, unless they are flattened
+    let added_paths = if path.is_dir() && sub_matches.get_flag("flatten") {
+        flat_file_array(path.to_path_buf())
     } else {
-        config_struct
+        vec![path.to_path_buf()]
+    };
+
+    let tags = sub_matches
+        .get_many::<String>("tag")
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect::<Vec<String>>();
+
+    for added_path in added_paths {
+        let added_path = match absolute(&added_path) {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!(
+                    "ERROR: Failed to resolve the path `{:#?}`, because of the following error:\n{}",
+                    added_path, e
+                );
+                exit(1);
+            }
+        };
+
+        let mut linked_file = LinkedFile::new(added_path, None);
+        linked_file.tags = tags.clone();
+
+        for file in linked_file.expand(&config_struct.home_path) {
+            if read(&file.source).is_ok_and(|x| as_text(&x).is_none()) {
+                println!(
+                    "INFO: `{:#?}` is a binary file, which is copied as it is instead of being rendered.",
+                    file.source
+                );
+            }
+        }
+
+        // Managed sources may be written with `~` or variables
+        if config_struct
             .managed_paths
-            .push(LinkedFile::new(path, None));
-    }
+            .iter()
+            .any(|x| x.has_source(&linked_file.source))
+        {
+            eprintln!("ERROR: A config with the same path is already managed by DotMan.\nYou can print all managed configs by using the `source list` command.");
+            exit(1);
+        }
 
-    if !has_unique_elements(config_struct.managed_paths.iter()) {
-        eprintln!("ERROR: A config with the same path is already managed by DotMan.\nYou can print all managed configs by using the `source list` command.");
-        exit(1);
+        // Written relative to the home directory, so the config can be shared with other machines
+        linked_file.source = portable_path(&linked_file.source);
+        config_struct.managed_paths.push(linked_file);
     }
 
     write_config(&config_struct);
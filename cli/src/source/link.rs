@@ -1,7 +1,8 @@
 use clap::ArgMatches;
 use std::{path::PathBuf, process::exit};
+use template_engine::paths::portable_path;
 
-use crate::{get_config_file_content, utils::write_config};
+use crate::{config::DeployStrategy, get_config_file_content, utils::write_config};
 
 pub fn link(sub_matches: &ArgMatches) {
     let source_path = sub_matches
@@ -12,14 +13,20 @@ pub fn link(sub_matches: &ArgMatches) {
         .get_one::<PathBuf>("destination_path")
         .expect("ERROR: Invalid path supplied.");
 
+    // Safe unwrap: The argument has a default value and only accepts known strategies
+    let strategy = sub_matches
+        .get_one::<String>("strategy")
+        .unwrap()
+        .parse::<DeployStrategy>()
+        .unwrap();
+
     if !source_path.exists() {
         eprintln!("ERROR: Source path does not exist.");
         exit(1);
     }
 
     if destination_path.exists() {
-        eprintln!("ERROR: Destination path already exists.");
-        exit(1);
+        println!("WARNING: Destination path already exists.\nUse `apply --force` to back it up and replace it.");
     }
 
     let mut config_struct = get_config_file_content();
@@ -30,7 +37,7 @@ pub fn link(sub_matches: &ArgMatches) {
         .managed_paths
         .into_iter()
         .filter_map(|mut linked_file| {
-            if linked_file.source == *source_path {
+            if linked_file.has_source(source_path) {
                 if was_modified {
                     eprintln!("ERROR: Source path `{:#?}` is used mulitple times.\nPlease remove the duplicates.\nThe first occurence of the path was linked.", source_path);
                     exit(1);
@@ -39,7 +46,10 @@ pub fn link(sub_matches: &ArgMatches) {
                     println!("WARNING: Source file already had a link  that now changed.");
                 }
                 was_modified = true;
-                linked_file.destination = Some(destination_path.to_path_buf());
+                // Written relative to the home directory, so the config can be shared with other machines
+                linked_file.source = portable_path(&linked_file.source);
+                linked_file.destination = Some(portable_path(destination_path));
+                linked_file.strategy = strategy;
             }
 
             Some(linked_file)
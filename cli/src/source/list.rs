@@ -10,8 +10,16 @@ pub fn list() {
         exit(0);
     }
 
-    for (location, destination) in config_content.paths_iter() {
-        match destination {
+    for linked_file in &config_content.managed_paths {
+        let location = &linked_file.source;
+
+        match &linked_file.destination {
+            Some(destination) if !linked_file.strategy.is_copy() => {
+                println!(
+                    "Location: {:#?} -> Destination: {:#?} ({:?})",
+                    location, destination, linked_file.strategy
+                )
+            }
             Some(destination) => {
                 println!(
                     "Location: {:#?} -> Destination: {:#?}",
@@ -20,5 +28,9 @@ pub fn list() {
             }
             None => println!("Location: {:#?}", location),
         }
+
+        if !linked_file.tags.is_empty() {
+            println!("    Tags: {}", linked_file.tags.join(", "));
+        }
     }
 }
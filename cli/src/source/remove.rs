@@ -15,7 +15,7 @@ pub fn remove(sub_matches: &ArgMatches) {
         .managed_paths
         .iter()
         .enumerate()
-        .find(|x| x.1.source == *path);
+        .find(|x| x.1.has_source(path));
 
     match index {
         Some((index, _)) => {
@@ -2,7 +2,7 @@ use std::path::PathBuf;
 
 use clap::ArgMatches;
 
-use crate::{get_config_file_content, write_config};
+use crate::{config::DeployStrategy, get_config_file_content, write_config};
 
 pub fn unlink(sub_matches: &ArgMatches) {
     let path = sub_matches
@@ -15,8 +15,9 @@ pub fn unlink(sub_matches: &ArgMatches) {
         .managed_paths
         .into_iter()
         .map(|mut linked_file| {
-            if linked_file.source == *path {
+            if linked_file.has_source(path) {
                 linked_file.destination = None;
+                linked_file.strategy = DeployStrategy::default();
             }
             linked_file
         })
@@ -0,0 +1,290 @@
+use std::{
+    fs::{
+        read, read_dir, read_to_string, remove_file, set_permissions, DirBuilder, OpenOptions,
+        Permissions,
+    },
+    io::{self, Write},
+    os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt},
+    path::{Path, PathBuf},
+    process::exit,
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{transaction::write_atomic, utils::data_dir};
+
+/// What `apply` deployed to a destination.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AppliedFile {
+    pub destination: PathBuf,
+    pub source: PathBuf,
+    /// SHA-256 of the written content. Missing for links and for paths migrated from `applied_paths`
+    pub hash: Option<String>,
+    /// SHA-256 of the source at the time it was applied
+    #[serde(default)]
+    pub source_hash: Option<String>,
+    /// SHA-256 of the variables a template used when it was rendered. Missing for files that are not rendered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variables_hash: Option<String>,
+    /// SHA-256 of the local version kept instead of the written content, until the source or its variables change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kept_hash: Option<String>,
+    /// RFC 3339 timestamp of the last write
+    pub applied_at: String,
+}
+
+/// The state of all destinations deployed by DotMan, stored in the data directory.
+#[derive(Serialize, Deserialize, Default)]
+pub struct StateFile {
+    /// Sorted by destination
+    #[serde(default)]
+    pub files: Vec<AppliedFile>,
+    /// Parent directories created by `apply`, which are removed by `unapply` once they are empty
+    #[serde(default)]
+    pub created_dirs: Vec<PathBuf>,
+}
+
+pub fn state_file_path() -> PathBuf {
+    data_dir().join("state.toml")
+}
+
+/// The content of every copied destination as it was last applied, named by its hash.
+/// It is the base of three-way merges when a destination and its source both changed.
+pub fn bases_dir() -> PathBuf {
+    data_dir().join("bases")
+}
+
+/// Stores applied content as a base for later merges. Bases can hold secrets like the content of
+/// `~/.ssh/config`, so they are only readable by the user, whatever the mode of their destination is.
+pub fn store_base(bases_dir: &Path, content_hash: &str, content: &[u8]) -> io::Result<()> {
+    DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(bases_dir)?;
+    // Directories created by earlier versions have the default permissions
+    set_permissions(bases_dir, Permissions::from_mode(0o700))?;
+
+    let path = bases_dir.join(content_hash);
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&path)?;
+    // The mode is only used for new files
+    file.set_permissions(Permissions::from_mode(0o600))?;
+
+    file.write_all(content)
+}
+
+pub fn hash(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// The hash of the current content of a file, if it can be read.
+pub fn file_hash(path: &Path) -> Option<String> {
+    read(path).ok().map(|x| hash(&x))
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+impl StateFile {
+    /// Reads the state file. A missing state file is treated as empty.
+    pub fn read() -> Self {
+        let path = state_file_path();
+
+        if !path.exists() {
+            return Self::default();
+        }
+
+        let content = match read_to_string(&path) {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!(
+                    "ERROR: Failed to read state file at `{:#?}`, because of the following error:\n{}",
+                    path, e
+                );
+                exit(1);
+            }
+        };
+
+        match toml::from_str::<Self>(&content) {
+            Ok(mut x) => {
+                // Lookups rely on the order, which may have been changed by hand
+                x.files.sort_by(|a, b| a.destination.cmp(&b.destination));
+                x
+            }
+            Err(e) => {
+                eprintln!(
+                    "ERROR: Failed to parse state file at `{:#?}`, because of the following error:\n{}",
+                    path, e
+                );
+                exit(1);
+            }
+        }
+    }
+
+    pub fn write(&self) {
+        let path = state_file_path();
+        // Safe unwrap: Data is known
+        let content = toml::to_string(self).unwrap();
+
+        if let Err(e) = write_atomic(&path, content.as_bytes()) {
+            eprintln!(
+                "ERROR: Failed to write state file at `{:#?}`, because of the following error:\n{}",
+                path, e
+            );
+            exit(1);
+        }
+
+        self.prune_bases();
+    }
+
+    /// Removes stored bases that no destination refers to anymore.
+    fn prune_bases(&self) {
+        let Ok(entries) = read_dir(bases_dir()) else {
+            return;
+        };
+
+        for entry in entries.filter_map(Result::ok) {
+            let name = entry.file_name().to_string_lossy().to_string();
+
+            if !self.files.iter().any(|x| x.hash.as_ref() == Some(&name)) {
+                let _ = remove_file(entry.path());
+            }
+        }
+    }
+
+    /// The position of the destination in `files`, or where it would be inserted.
+    fn position(&self, destination: &Path) -> Result<usize, usize> {
+        self.files
+            .binary_search_by(|x| x.destination.as_path().cmp(destination))
+    }
+
+    pub fn get(&self, destination: &Path) -> Option<&AppliedFile> {
+        let destination = normalize(destination);
+
+        self.position(&destination)
+            .ok()
+            .map(|index| &self.files[index])
+    }
+
+    /// Whether the destination was deployed by DotMan.
+    pub fn is_applied(&self, destination: &Path) -> bool {
+        self.get(destination).is_some()
+    }
+
+    /// Records a write to `destination`, replacing its previous entry.
+    pub fn record(&mut self, destination: &Path, source: &Path, hash: Option<String>) {
+        self.insert(AppliedFile {
+            destination: normalize(destination),
+            source: normalize(source),
+            hash,
+            source_hash: file_hash(source),
+            variables_hash: None,
+            kept_hash: None,
+            applied_at: chrono::Local::now().to_rfc3339(),
+        });
+    }
+
+    fn insert(&mut self, applied_file: AppliedFile) {
+        match self.position(&applied_file.destination) {
+            Ok(index) => self.files[index] = applied_file,
+            Err(index) => self.files.insert(index, applied_file),
+        }
+    }
+
+    /// Records `content` as written to `destination` and stores it as the base for later merges.
+    pub fn record_content(&mut self, destination: &Path, source: &Path, content: &[u8]) {
+        self.record_render(destination, source, content, None);
+    }
+
+    /// Like `record_content`, but also records the hash of the variables the content was rendered with,
+    /// so the next `apply` can skip the destination while neither its source nor these variables change.
+    pub fn record_render(
+        &mut self,
+        destination: &Path,
+        source: &Path,
+        content: &[u8],
+        variables_hash: Option<String>,
+    ) {
+        let content_hash = hash(content);
+
+        if let Err(e) = store_base(&bases_dir(), &content_hash, content) {
+            println!(
+                "WARNING: Failed to store the applied content of `{:#?}`, because of the following error:\n{}\nConflicts can only be merged without a base.",
+                destination, e
+            );
+        }
+
+        self.insert(AppliedFile {
+            destination: normalize(destination),
+            source: normalize(source),
+            hash: Some(content_hash),
+            source_hash: file_hash(source),
+            variables_hash,
+            kept_hash: None,
+            applied_at: chrono::Local::now().to_rfc3339(),
+        });
+    }
+
+    /// Records the rendered source like `record_render`, but keeps the local version of `destination`.
+    /// `apply` leaves the destination alone while it still has this local version and the source and
+    /// its variables do not change.
+    pub fn record_kept(
+        &mut self,
+        destination: &Path,
+        source: &Path,
+        rendered: &[u8],
+        variables_hash: Option<String>,
+    ) {
+        self.record_render(destination, source, rendered, variables_hash);
+
+        let kept_hash = file_hash(destination);
+        if let Ok(index) = self.position(&normalize(destination)) {
+            self.files[index].kept_hash = kept_hash;
+        }
+    }
+
+    /// The content last applied to `destination`, if it was stored.
+    pub fn base(&self, destination: &Path) -> Option<String> {
+        let recorded_hash = self.get(destination)?.hash.as_ref()?;
+
+        read_to_string(bases_dir().join(recorded_hash)).ok()
+    }
+
+    pub fn remove(&mut self, destination: &Path) {
+        if let Ok(index) = self.position(&normalize(destination)) {
+            self.files.remove(index);
+        }
+    }
+
+    /// Whether the destination was edited since it was last written by DotMan.
+    /// Files without a recorded hash or that do not exist anymore never count as drifted.
+    pub fn has_drifted(&self, destination: &Path) -> bool {
+        let Some(recorded_hash) = self.get(destination).and_then(|x| x.hash.as_ref()) else {
+            return false;
+        };
+
+        file_hash(destination).is_some_and(|x| &x != recorded_hash)
+    }
+
+    /// Moves the destinations of the deprecated `applied_paths` config field into the state file.
+    /// Their source is unknown, so only ownership is carried over.
+    /// Returns whether there were paths to migrate, i.e. whether the config has to be written.
+    pub fn migrate(&mut self, applied_paths: &mut Vec<PathBuf>) -> bool {
+        let migrated = !applied_paths.is_empty();
+
+        for path in applied_paths.drain(..) {
+            if !self.is_applied(&path) {
+                self.record(&path, Path::new(""), None);
+            }
+        }
+
+        migrated
+    }
+}
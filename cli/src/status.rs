@@ -0,0 +1,207 @@
+use std::{
+    fs::{read, symlink_metadata},
+    path::{Path, PathBuf},
+    process::{exit, Command},
+};
+
+use clap::ArgMatches;
+use serde::Serialize;
+use template_engine::{analysis::is_templated, TemplateEngine};
+
+use crate::{
+    apply::{link_state, LinkState},
+    config::LinkedFile,
+    render::{condition_holds, variables_engine, variables_hash},
+    selection::Selection,
+    state::{file_hash, StateFile},
+    utils::{as_text, get_config_file_content},
+};
+
+/// The state of a `LinkedFile` compared to what was last applied.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    /// The file has no destination
+    Unlinked,
+    /// The condition of the file is false on this machine, so it is not deployed
+    Inactive,
+    NotApplied,
+    UpToDate,
+    SourceChanged,
+    DestinationEdited,
+    DestinationMissing,
+}
+
+impl FileStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            FileStatus::Unlinked => "unlinked",
+            FileStatus::Inactive => "inactive",
+            FileStatus::NotApplied => "not applied",
+            FileStatus::UpToDate => "up to date",
+            FileStatus::SourceChanged => "source changed",
+            FileStatus::DestinationEdited => "destination edited",
+            FileStatus::DestinationMissing => "destination missing",
+        }
+    }
+}
+
+/// Determines the status of a managed file from the state file and the file system.
+/// A destination edited by hand takes precedence over a changed source.
+/// Templates also count as changed if the variables they use changed, which is only known if an engine holding them is given.
+pub fn file_status(
+    linked_file: &LinkedFile,
+    state: &StateFile,
+    variables_engine: Option<&TemplateEngine>,
+) -> FileStatus {
+    let Some(destination) = &linked_file.destination else {
+        return FileStatus::Unlinked;
+    };
+
+    let Some(applied_file) = state.get(destination) else {
+        return FileStatus::NotApplied;
+    };
+
+    if symlink_metadata(destination).is_err() {
+        return FileStatus::DestinationMissing;
+    }
+
+    if !linked_file.strategy.is_copy() {
+        return match link_state(&linked_file.source, destination, linked_file.strategy) {
+            LinkState::Correct => FileStatus::UpToDate,
+            LinkState::Missing => FileStatus::DestinationMissing,
+            LinkState::Unexpected(_) => FileStatus::DestinationEdited,
+        };
+    }
+
+    if state.has_drifted(destination) {
+        return FileStatus::DestinationEdited;
+    }
+
+    match &applied_file.source_hash {
+        Some(source_hash) if file_hash(&linked_file.source).as_ref() != Some(source_hash) => {
+            return FileStatus::SourceChanged;
+        }
+        _ => (),
+    }
+
+    let Some(variables_engine) = variables_engine else {
+        return FileStatus::UpToDate;
+    };
+
+    // Same as `apply`, files without expressions render to the same content for any variables
+    let variables_hash = read(&linked_file.source)
+        .ok()
+        .and_then(|x| as_text(&x))
+        .filter(|x| is_templated(x))
+        .map(|x| variables_hash(variables_engine, &x));
+
+    match applied_file.variables_hash == variables_hash {
+        true => FileStatus::UpToDate,
+        false => FileStatus::SourceChanged,
+    }
+}
+
+#[derive(Serialize)]
+struct FileReport {
+    source: PathBuf,
+    destination: Option<PathBuf>,
+    status: FileStatus,
+}
+
+#[derive(Serialize)]
+struct StatusReport {
+    files: Vec<FileReport>,
+    /// Output of `git status --porcelain` in the dotman home, missing if it is not a git repository
+    git: Option<Vec<String>>,
+}
+
+/// The changed files of the git repository in the dotman home.
+fn git_status(home_path: &Path) -> Option<Vec<String>> {
+    let output = Command::new("git")
+        .current_dir(home_path)
+        .arg("status")
+        .arg("--porcelain")
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+pub fn status(matches: &ArgMatches) {
+    let json = matches.get_flag("json");
+
+    let selection = Selection::from_matches(matches);
+
+    let config_struct = get_config_file_content();
+    let state = StateFile::read();
+
+    let linked_files = config_struct.linked_files();
+
+    // Sources that fail to evaluate are reported by `apply`, here conditions and variables are ignored then
+    let variables_engine = variables_engine(&config_struct).ok();
+
+    let is_inactive = |linked_file: &LinkedFile| {
+        variables_engine
+            .as_ref()
+            .is_some_and(|x| condition_holds(linked_file, x).is_ok_and(|x| !x))
+    };
+
+    let report = StatusReport {
+        files: linked_files
+            .iter()
+            .filter(|x| selection.matches(x))
+            .map(|linked_file| FileReport {
+                source: linked_file.source.clone(),
+                destination: linked_file.destination.clone(),
+                status: match is_inactive(linked_file) {
+                    true => FileStatus::Inactive,
+                    false => file_status(linked_file, &state, variables_engine.as_ref()),
+                },
+            })
+            .collect(),
+        git: git_status(&config_struct.home_path),
+    };
+
+    if json {
+        // Safe unwrap: Data is known
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+        exit(0);
+    }
+
+    if report.files.is_empty() {
+        println!("INFO: There are currently no configs managed by DotMan.\nYou can add a config by using the `source add` command.");
+    }
+
+    for file in &report.files {
+        match &file.destination {
+            Some(destination) => println!(
+                "{:<20} {:#?} -> {:#?}",
+                file.status.label(),
+                file.source,
+                destination
+            ),
+            None => println!("{:<20} {:#?}", file.status.label(), file.source),
+        }
+    }
+
+    match report.git {
+        Some(changes) if changes.is_empty() => println!("\nGit: Working tree clean."),
+        Some(changes) => {
+            println!("\nGit: {} uncommitted change(s):", changes.len());
+            for change in changes {
+                println!("    {}", change);
+            }
+        }
+        None => println!("\nGit: The dotman home is not a git repository."),
+    }
+}
@@ -0,0 +1,162 @@
+use std::{
+    fs::{read, read_to_string, write},
+    process::exit,
+};
+
+use clap::ArgMatches;
+use template_engine::analysis::is_templated;
+
+use crate::{
+    diff::{changed_lines, print_diff},
+    render::render_template,
+    selection::Selection,
+    state::StateFile,
+    status::{file_status, FileStatus},
+    utils::{as_text, confirm, get_config_file_content, open_editor},
+};
+
+pub fn sync_back(matches: &ArgMatches) {
+    let selection = Selection::from_matches(matches);
+
+    let config_struct = get_config_file_content();
+    let mut state = StateFile::read();
+
+    let mut failed = false;
+    let mut pending = false;
+    let mut synced_count = 0;
+
+    for linked_file in &config_struct.linked_files() {
+        // Links already share their content with the source
+        let Some(destination) = &linked_file.destination else {
+            continue;
+        };
+
+        if !linked_file.strategy.is_copy()
+            || !selection.matches(linked_file)
+            || file_status(linked_file, &state, None) != FileStatus::DestinationEdited
+        {
+            continue;
+        }
+
+        let source = &linked_file.source;
+
+        let (destination_content, source_content) = match (read(destination), read(source)) {
+            (Ok(x), Ok(y)) => (x, y),
+            (Err(e), _) | (_, Err(e)) => {
+                eprintln!(
+                        "ERROR: Failed to read `{:#?}` or its source `{:#?}`, because of the following error:\n{}",
+                        destination, source, e
+                    );
+                failed = true;
+                continue;
+            }
+        };
+
+        // Binary files are never rendered
+        let Some(source_content) = as_text(&source_content).filter(|x| is_templated(x)) else {
+            if let Err(e) = write(source, &destination_content) {
+                eprintln!(
+                    "ERROR: Failed to write to source `{:#?}`, because of the following error:\n{}",
+                    source, e
+                );
+                failed = true;
+                continue;
+            }
+
+            state.record_content(destination, source, &destination_content);
+
+            println!("INFO: Copied `{:#?}` back to `{:#?}`.", destination, source);
+            synced_count += 1;
+            continue;
+        };
+
+        let destination_content = String::from_utf8_lossy(&destination_content).to_string();
+
+        // Copying the rendered file would replace the template logic with the values of this machine
+        let rendered = match render_template(&config_struct, source_content) {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!(
+                    "ERROR: Received the following error while rendering `{:#?}`:\n{}",
+                    source, e
+                );
+                failed = true;
+                continue;
+            }
+        };
+
+        // The changes were already merged into the template by hand
+        if rendered == destination_content {
+            state.record_content(destination, source, destination_content.as_bytes());
+
+            println!(
+                "INFO: The template `{:#?}` already renders to `{:#?}`.",
+                source, destination
+            );
+            synced_count += 1;
+            continue;
+        }
+
+        // The last applied content, so changed variables are not mistaken for local edits
+        let base = state.base(destination).unwrap_or_else(|| rendered.clone());
+
+        println!(
+            "INFO: `{:#?}` is rendered from the template `{:#?}` and has the following local changes:",
+            destination, source
+        );
+        print_diff(
+            &base,
+            &destination_content,
+            "applied",
+            &destination.to_string_lossy(),
+        );
+
+        if !confirm("Open the template in your editor to merge these changes?") {
+            println!(
+                "WARNING: Templates are never overwritten. Merge the changes into `{:#?}` by hand. Skipping...",
+                source
+            );
+            pending = true;
+            continue;
+        }
+
+        open_editor(source);
+
+        let merged = read_to_string(source)
+            .map_err(anyhow::Error::from)
+            .and_then(|x| render_template(&config_struct, x));
+
+        if merged.is_ok_and(|x| contains_edits(&base, &destination_content, &rendered, &x)) {
+            state.record_content(destination, source, destination_content.as_bytes());
+
+            println!(
+                "INFO: Merged the changes of `{:#?}` into `{:#?}`.",
+                destination, source
+            );
+            synced_count += 1;
+        } else {
+            println!(
+                "WARNING: The changes of `{:#?}` are still missing from the template `{:#?}`. Run `sync-back` again or use `apply --force` to discard them.",
+                destination, source
+            );
+            pending = true;
+        }
+    }
+
+    state.write();
+
+    if synced_count == 0 && !pending && !failed {
+        println!("INFO: No deployed file was edited since it was last applied.");
+    }
+
+    if failed || pending {
+        exit(1);
+    }
+}
+
+/// Whether the template renders with the local edits of the destination, which are its changes from `base`.
+/// The template rendered to `previous` before it was edited, so changes of the variables since the last
+/// apply only count as edits where the destination changed as well.
+pub fn contains_edits(base: &str, destination: &str, previous: &str, rendered: &str) -> bool {
+    rendered == destination || changed_lines(base, destination) == changed_lines(previous, rendered)
+}
@@ -0,0 +1,85 @@
+use std::{
+    ffi::OsStr,
+    fs::{read_to_string, write},
+    path::PathBuf,
+    process::exit,
+};
+
+use clap::ArgMatches;
+use template_engine::format::format_file;
+
+use crate::{flat_file_array, get_config_file_content};
+
+pub fn fmt(sub_matches: &ArgMatches) {
+    let check = sub_matches.get_flag("check");
+
+    let paths: Vec<PathBuf> = match sub_matches.get_many::<PathBuf>("paths") {
+        Some(paths) => paths
+            .flat_map(|path| {
+                if path.is_dir() {
+                    flat_file_array(path.to_path_buf())
+                } else {
+                    vec![path.to_path_buf()]
+                }
+            })
+            .collect(),
+        None => get_config_file_content()
+            .paths_iter()
+            .map(|(source, _)| source)
+            .collect(),
+    };
+
+    let mut failed = false;
+
+    for path in paths {
+        let content = match read_to_string(&path) {
+            Ok(x) => x,
+            Err(_) => {
+                println!(
+                    "WARNING: File with path `{:#?}` could not be read as text. Skipping...",
+                    path
+                );
+                continue;
+            }
+        };
+
+        let source = path.extension() == Some(OsStr::new("te"));
+
+        let formatted = match format_file(&content, source) {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!(
+                    "ERROR: Failed to format file with path `{:#?}`, because of the following error:\n{}",
+                    path, e
+                );
+                failed = true;
+                continue;
+            }
+        };
+
+        if formatted == content {
+            continue;
+        }
+
+        if check {
+            println!("File with path `{:#?}` is not formatted.", path);
+            failed = true;
+            continue;
+        }
+
+        match write(&path, formatted) {
+            Ok(_) => println!("INFO: Formatted `{:#?}`.", path),
+            Err(e) => {
+                eprintln!(
+                    "ERROR: Failed to write formatted content to `{:#?}`, because of the following error:\n{}",
+                    path, e
+                );
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        exit(1);
+    }
+}
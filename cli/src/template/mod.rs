@@ -0,0 +1,19 @@
+use std::process::exit;
+
+use clap::ArgMatches;
+
+mod fmt;
+
+pub fn template(matches: &ArgMatches) {
+    match matches.subcommand() {
+        Some(("fmt", sub_matches)) => fmt::fmt(sub_matches),
+        Some((subcommand, _)) => {
+            eprintln!("Unknown subcommand {}", subcommand);
+            exit(1);
+        }
+        None => {
+            eprintln!("No subcommand provided");
+            exit(1);
+        }
+    }
+}
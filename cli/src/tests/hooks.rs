@@ -0,0 +1,32 @@
+use std::{
+    env::temp_dir,
+    time::{Duration, Instant},
+};
+
+use crate::hooks::run_hook;
+
+#[test]
+fn hook_with_background_child() {
+    let start = Instant::now();
+
+    assert!(run_hook(
+        "echo post; sleep 30 &",
+        &temp_dir(),
+        Duration::from_secs(5)
+    ));
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn hook_timeout() {
+    let start = Instant::now();
+
+    assert!(!run_hook("sleep 30", &temp_dir(), Duration::from_secs(1)));
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn failing_hook() {
+    assert!(run_hook("true", &temp_dir(), Duration::from_secs(5)));
+    assert!(!run_hook("exit 3", &temp_dir(), Duration::from_secs(5)));
+}
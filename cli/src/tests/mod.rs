@@ -0,0 +1,6 @@
+pub mod hooks;
+pub mod render;
+pub mod selection;
+pub mod state;
+pub mod sync_back;
+pub mod watch;
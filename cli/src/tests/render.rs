@@ -0,0 +1,103 @@
+use anyhow::Result;
+use template_engine::TemplateEngine;
+
+use crate::render::variables_hash;
+
+const TEMPLATE: &str = "font = Iosevka\n{{ $color }}\n";
+
+fn engine(source: &str) -> Result<TemplateEngine> {
+    let mut engine = TemplateEngine::default();
+
+    engine.evaluate_source_file(source.to_string())?;
+
+    Ok(engine)
+}
+
+#[test]
+fn same_variables() -> Result<()> {
+    assert_eq!(
+        variables_hash(&engine("$color = \"red\"")?, TEMPLATE),
+        variables_hash(&engine("$color = \"red\"")?, TEMPLATE)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn used_variable_changed() -> Result<()> {
+    assert_ne!(
+        variables_hash(&engine("$color = \"red\"")?, TEMPLATE),
+        variables_hash(&engine("$color = \"blue\"")?, TEMPLATE)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn unused_variable_changed() -> Result<()> {
+    assert_eq!(
+        variables_hash(&engine("$color = \"red\"\n$size = \"12\"")?, TEMPLATE),
+        variables_hash(&engine("$color = \"red\"\n$size = \"14\"")?, TEMPLATE)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn variable_only_assigned() -> Result<()> {
+    // Assigning a variable in the template does not use its value from the sources
+    let template = "{{ $color = \"green\" }}\nplain\n";
+
+    assert_eq!(
+        variables_hash(&engine("$color = \"red\"")?, template),
+        variables_hash(&engine("$color = \"blue\"")?, template)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn variable_removed() -> Result<()> {
+    assert_ne!(
+        variables_hash(&engine("$color = \"red\"")?, TEMPLATE),
+        variables_hash(&engine("")?, TEMPLATE)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn list_item_changed() -> Result<()> {
+    let template = "{{ for $font in $fonts }}\n{{ $font }}\n{{ end }}\n";
+
+    assert_ne!(
+        variables_hash(&engine("$fonts = [\"Iosevka\"]")?, template),
+        variables_hash(&engine("$fonts = [\"Iosevka\", \"Noto Sans\"]")?, template)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn hostname_and_profile_changed() -> Result<()> {
+    // `host` and `profile` blocks use them without referencing them
+    let mut desktop = engine("")?;
+    desktop.set_hostname("desktop");
+    let mut laptop = engine("")?;
+    laptop.set_hostname("laptop");
+
+    assert_ne!(
+        variables_hash(&desktop, "plain\n"),
+        variables_hash(&laptop, "plain\n")
+    );
+
+    let mut work = engine("")?;
+    work.set_profile("work");
+
+    assert_ne!(
+        variables_hash(&engine("")?, "plain\n"),
+        variables_hash(&work, "plain\n")
+    );
+
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use crate::{cli::cli, config::LinkedFile, selection::Selection};
+
+fn selection(args: &[&str]) -> Selection {
+    let matches = cli().get_matches_from(["dotman", "status"].iter().chain(args));
+
+    Selection::from_matches(matches.subcommand_matches("status").unwrap())
+}
+
+fn linked_file(source: &str, destination: Option<&str>, tags: &[&str]) -> LinkedFile {
+    let mut linked_file = LinkedFile::new(source, destination);
+    linked_file.tags = tags.iter().map(|x| x.to_string()).collect();
+
+    linked_file
+}
+
+#[test]
+fn select_everything() {
+    let selection = selection(&[]);
+
+    assert!(selection.matches(&linked_file("dots/kitty.conf", None, &[])));
+    assert!(selection.matches(&linked_file("dots/zshrc", Some("/tmp/.zshrc"), &["shell"])));
+}
+
+#[test]
+fn select_by_prefix() {
+    let selection = selection(&["dots/kitty"]);
+
+    assert!(selection.matches(&linked_file("dots/kitty", None, &[])));
+    assert!(selection.matches(&linked_file("dots/kitty/kitty.conf", None, &[])));
+    // Only whole components are a prefix
+    assert!(!selection.matches(&linked_file("dots/kitty-themes/dark.conf", None, &[])));
+    assert!(!selection.matches(&linked_file("dots/zshrc", None, &[])));
+}
+
+#[test]
+fn select_by_destination() {
+    let selection = selection(&["/tmp/config"]);
+
+    assert!(selection.matches(&linked_file(
+        "dots/kitty.conf",
+        Some("/tmp/config/kitty/kitty.conf"),
+        &[]
+    )));
+    assert!(!selection.matches(&linked_file("dots/kitty.conf", Some("/tmp/.zshrc"), &[])));
+}
+
+#[test]
+fn select_by_name_glob() {
+    let selection = selection(&["*.conf"]);
+
+    // Patterns without a `/` match the file name in any directory
+    assert!(selection.matches(&linked_file("dots/kitty.conf", None, &[])));
+    assert!(selection.matches(&linked_file("dots/kitty/kitty.conf", None, &[])));
+    assert!(selection.matches(&linked_file("dots/zshrc", Some("/tmp/zsh.conf"), &[])));
+    assert!(!selection.matches(&linked_file("dots/kitty.conf.bak", None, &[])));
+}
+
+#[test]
+fn select_by_path_glob() {
+    let selection = selection(&["dots/*.conf"]);
+
+    // `*` does not match the `/` between directories
+    assert!(selection.matches(&linked_file("dots/kitty.conf", None, &[])));
+    assert!(!selection.matches(&linked_file("dots/kitty/kitty.conf", None, &[])));
+}
+
+#[test]
+fn select_by_recursive_path_glob() {
+    let selection = selection(&["dots/**/*.conf"]);
+
+    assert!(selection.matches(&linked_file("dots/kitty/kitty.conf", None, &[])));
+    assert!(selection.matches(&linked_file("dots/kitty/themes/dark.conf", None, &[])));
+}
+
+#[test]
+fn glob_is_not_a_prefix() {
+    let selection = selection(&["dots/kit*"]);
+
+    assert!(selection.matches(&linked_file("dots/kitty", None, &[])));
+    assert!(!selection.matches(&linked_file("dots/kitty/kitty.conf", None, &[])));
+}
+
+#[test]
+fn select_by_tag() {
+    let selection = selection(&["--tag", "shell", "--tag", "wm"]);
+
+    assert!(selection.matches(&linked_file("dots/zshrc", None, &["shell"])));
+    assert!(selection.matches(&linked_file("dots/sway", None, &["wm", "desktop"])));
+    assert!(!selection.matches(&linked_file("dots/kitty.conf", None, &[])));
+}
+
+#[test]
+fn select_by_path_and_tag() {
+    let selection = selection(&["--tag", "shell", "*.conf"]);
+
+    assert!(selection.matches(&linked_file("dots/zsh.conf", None, &["shell"])));
+    assert!(!selection.matches(&linked_file("dots/zshrc", None, &["shell"])));
+    assert!(!selection.matches(&linked_file("dots/kitty.conf", None, &[])));
+}
@@ -0,0 +1,48 @@
+use std::{
+    env::temp_dir,
+    fs::{create_dir_all, metadata, read, remove_dir_all, set_permissions, write, Permissions},
+    os::unix::fs::PermissionsExt,
+    path::Path,
+    process,
+};
+
+use crate::state::{hash, store_base};
+
+fn mode(path: &Path) -> u32 {
+    metadata(path).unwrap().permissions().mode() & 0o7777
+}
+
+#[test]
+fn bases_are_private() {
+    let bases_dir = temp_dir().join(format!("dotman-bases-{}-private", process::id()));
+    let _ = remove_dir_all(&bases_dir);
+
+    let content = b"Host example.com\n    IdentityFile ~/.ssh/secret\n";
+    store_base(&bases_dir, &hash(content), content).unwrap();
+
+    assert_eq!(mode(&bases_dir), 0o700);
+    assert_eq!(mode(&bases_dir.join(hash(content))), 0o600);
+    assert_eq!(read(bases_dir.join(hash(content))).unwrap(), content);
+
+    remove_dir_all(bases_dir).unwrap();
+}
+
+#[test]
+fn existing_bases_become_private() {
+    let bases_dir = temp_dir().join(format!("dotman-bases-{}-existing", process::id()));
+    let _ = remove_dir_all(&bases_dir);
+
+    // Bases stored by earlier versions have the default permissions
+    let content = b"token = secret\n";
+    create_dir_all(&bases_dir).unwrap();
+    set_permissions(&bases_dir, Permissions::from_mode(0o755)).unwrap();
+    write(bases_dir.join(hash(content)), content).unwrap();
+    set_permissions(bases_dir.join(hash(content)), Permissions::from_mode(0o644)).unwrap();
+
+    store_base(&bases_dir, &hash(content), content).unwrap();
+
+    assert_eq!(mode(&bases_dir), 0o700);
+    assert_eq!(mode(&bases_dir.join(hash(content))), 0o600);
+
+    remove_dir_all(bases_dir).unwrap();
+}
@@ -0,0 +1,41 @@
+use crate::sync_back::contains_edits;
+
+const BASE: &str = "color = red\nfont = Iosevka\n";
+const DESTINATION: &str = "color = red\nfont = Noto Sans\n";
+
+#[test]
+fn edits_merged() {
+    assert!(contains_edits(BASE, DESTINATION, BASE, DESTINATION));
+}
+
+#[test]
+fn edits_missing() {
+    assert!(!contains_edits(BASE, DESTINATION, BASE, BASE));
+}
+
+#[test]
+fn variables_changed_since_apply() {
+    // `$color` changed after the destination was applied, so the template renders differently from it
+    let previous = "color = blue\nfont = Iosevka\n";
+
+    assert!(!contains_edits(BASE, DESTINATION, previous, previous));
+    assert!(contains_edits(
+        BASE,
+        DESTINATION,
+        previous,
+        "color = blue\nfont = Noto Sans\n"
+    ));
+}
+
+#[test]
+fn edited_line_uses_changed_variable() {
+    // The edit can not be told apart from the new value of `$color`
+    let previous = "color = blue\nfont = Iosevka\n";
+
+    assert!(!contains_edits(
+        BASE,
+        "color = red # accent\nfont = Iosevka\n",
+        previous,
+        "color = blue # accent\nfont = Iosevka\n"
+    ));
+}
@@ -0,0 +1,147 @@
+use std::{
+    collections::HashSet,
+    env::temp_dir,
+    fs::{create_dir_all, remove_dir_all, remove_file, write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use template_engine::config::DataFile;
+
+use crate::{
+    config::{ConfigFile, LinkedFile},
+    watch::affected_sources,
+};
+
+/// A dotman home with sources that assign variables from each other and templates that use them:
+/// `colors.te` assigns `$accent`, `theme.te` assigns `$border` from it and `fonts.te` assigns `$font`.
+fn home(name: &str) -> (PathBuf, ConfigFile) {
+    let home_path = temp_dir().join(format!("dotman-watch-{}-{}", process::id(), name));
+    let _ = remove_dir_all(&home_path);
+    create_dir_all(&home_path).unwrap();
+    let home_path = home_path.canonicalize().unwrap();
+
+    let files = [
+        ("colors.te", "$accent = \"#89b4fa\""),
+        ("theme.te", "$border = $accent"),
+        ("fonts.te", "$font = \"Iosevka\""),
+        ("sway.conf", "{{ $border }}\n"),
+        ("kitty.conf", "{{ $font }}\n"),
+        ("waybar.css", "{{ $palette.bg }}\n"),
+        ("zshrc", "plain\n"),
+        ("palette.toml", "bg = \"#1e1e2e\"\n"),
+    ];
+
+    for (name, content) in files {
+        write(home_path.join(name), content).unwrap();
+    }
+
+    let mut config_struct = ConfigFile {
+        home_path: home_path.clone(),
+        data_files: vec![DataFile {
+            path: PathBuf::from("palette.toml"),
+            name: None,
+        }],
+        ..Default::default()
+    };
+
+    for source in ["colors.te", "theme.te", "fonts.te"] {
+        config_struct
+            .managed_paths
+            .push(LinkedFile::new(home_path.join(source), None));
+    }
+
+    for template in ["sway.conf", "kitty.conf", "waybar.css", "zshrc"] {
+        config_struct.managed_paths.push(LinkedFile::new(
+            home_path.join(template),
+            Some(home_path.join("out").join(template)),
+        ));
+    }
+
+    (home_path, config_struct)
+}
+
+fn affected(config_struct: &ConfigFile, home_path: &Path, changed: &[&str]) -> Vec<PathBuf> {
+    let changed_paths = changed
+        .iter()
+        .map(|x| home_path.join(x))
+        .collect::<HashSet<PathBuf>>();
+
+    affected_sources(config_struct, &changed_paths)
+}
+
+#[test]
+fn changed_template() {
+    let (home_path, config_struct) = home("template");
+
+    assert_eq!(
+        affected(&config_struct, &home_path, &["zshrc"]),
+        vec![home_path.join("zshrc")]
+    );
+
+    remove_dir_all(home_path).unwrap();
+}
+
+#[test]
+fn changed_source() {
+    let (home_path, config_struct) = home("source");
+
+    assert_eq!(
+        affected(&config_struct, &home_path, &["fonts.te"]),
+        vec![home_path.join("kitty.conf")]
+    );
+
+    remove_dir_all(home_path).unwrap();
+}
+
+#[test]
+fn transitive_variables() {
+    let (home_path, config_struct) = home("transitive");
+
+    // `$border` is assigned from `$accent`, so templates using it change as well
+    assert_eq!(
+        affected(&config_struct, &home_path, &["colors.te"]),
+        vec![home_path.join("sway.conf")]
+    );
+
+    remove_dir_all(home_path).unwrap();
+}
+
+#[test]
+fn changed_data_file() {
+    let (home_path, config_struct) = home("data");
+
+    assert_eq!(
+        affected(&config_struct, &home_path, &["palette.toml"]),
+        vec![home_path.join("waybar.css")]
+    );
+
+    remove_dir_all(home_path).unwrap();
+}
+
+#[test]
+fn unrelated_change() {
+    let (home_path, config_struct) = home("unrelated");
+
+    assert!(affected(&config_struct, &home_path, &["notes.md"]).is_empty());
+
+    remove_dir_all(home_path).unwrap();
+}
+
+#[test]
+fn removed_source() {
+    let (home_path, config_struct) = home("removed");
+    remove_file(home_path.join("fonts.te")).unwrap();
+
+    // The variables of a removed source are unknown, so every template using variables may be affected
+    assert_eq!(
+        affected(&config_struct, &home_path, &["fonts.te"]),
+        vec![
+            home_path.join("sway.conf"),
+            home_path.join("kitty.conf"),
+            home_path.join("waybar.css"),
+        ]
+    );
+
+    remove_dir_all(home_path).unwrap();
+}
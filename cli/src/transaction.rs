@@ -0,0 +1,267 @@
+use std::{
+    fs::{
+        copy, create_dir_all, hard_link, read_link, remove_dir, remove_file, rename,
+        symlink_metadata, File, OpenOptions,
+    },
+    io::{self, Write},
+    os::unix::fs::{symlink, OpenOptionsExt},
+    path::{Path, PathBuf},
+    process,
+    sync::{Mutex, PoisonError},
+};
+
+use crate::{backup::Backup, permissions::FileAttributes};
+
+/// A file replaced or created by a transaction.
+struct Change {
+    path: PathBuf,
+    /// The previous version of the file, kept next to it until the transaction is committed
+    original: Option<PathBuf>,
+    /// Whether the previous version is moved into the backup on commit
+    backup: bool,
+}
+
+/// Replaces files atomically and keeps their previous versions,
+/// so a failing `apply` can restore every file it already replaced.
+/// Files can be replaced from multiple threads at once.
+#[derive(Default)]
+pub struct Transaction {
+    changes: Mutex<Vec<Change>>,
+    /// Missing parent directories created for new files, outermost first
+    created_dirs: Mutex<Vec<PathBuf>>,
+}
+
+/// A hidden file next to `path`, so renaming it to `path` never crosses file systems.
+fn temp_path(path: &Path, kind: &str) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    path.with_file_name(format!(".{}.dotman-{}-{}", file_name, kind, process::id()))
+}
+
+/// Makes renames in the directory of `path` durable.
+fn sync_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => File::open(parent)?.sync_all(),
+        _ => Ok(()),
+    }
+}
+
+/// Replaces `path` with `content` without a transaction, e.g. for files only DotMan writes.
+/// The content is flushed to disk before the rename, so `path` holds either the old or the new content.
+pub fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    let temp = temp_path(path, "new");
+    let _ = remove_file(&temp);
+
+    let result = File::create_new(&temp).and_then(|mut file| {
+        file.write_all(content)?;
+        file.sync_all()
+    });
+
+    if let Err(e) = result.and_then(|_| rename(&temp, path)) {
+        let _ = remove_file(&temp);
+        return Err(e);
+    }
+
+    sync_parent(path)
+}
+
+/// Keeps the current version of `path` at `saved`. Symlinks are saved as symlinks.
+fn save_original(path: &Path, saved: &Path) -> io::Result<()> {
+    if symlink_metadata(path)?.file_type().is_symlink() {
+        return symlink(read_link(path)?, saved);
+    }
+
+    if hard_link(path, saved).is_err() {
+        copy(path, saved)?;
+    }
+
+    Ok(())
+}
+
+impl Transaction {
+    /// Creates the new version of `path` with `create` at a temporary path and renames it into place.
+    /// `backup` marks files not created by DotMan, whose previous version is backed up on commit.
+    fn replace_with<F>(&self, path: &Path, backup: bool, create: F) -> io::Result<()>
+    where
+        F: FnOnce(&Path) -> io::Result<()>,
+    {
+        self.create_parent_dirs(path)?;
+
+        let temp = temp_path(path, "new");
+        let _ = remove_file(&temp);
+
+        if let Err(e) = create(&temp) {
+            let _ = remove_file(&temp);
+            return Err(e);
+        }
+
+        let original = match symlink_metadata(path) {
+            Ok(_) => {
+                let saved = temp_path(path, "old");
+                let _ = remove_file(&saved);
+
+                if let Err(e) = save_original(path, &saved) {
+                    let _ = remove_file(&temp);
+                    return Err(e);
+                }
+
+                Some(saved)
+            }
+            Err(_) => None,
+        };
+
+        if let Err(e) = rename(&temp, path) {
+            let _ = remove_file(&temp);
+            if let Some(saved) = original {
+                let _ = remove_file(saved);
+            }
+            return Err(e);
+        }
+
+        self.changes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Change {
+                path: path.to_path_buf(),
+                original,
+                backup,
+            });
+
+        sync_parent(path)
+    }
+
+    /// Creates the missing ancestors of `path`.
+    fn create_parent_dirs(&self, path: &Path) -> io::Result<()> {
+        let Some(parent) = path.parent() else {
+            return Ok(());
+        };
+
+        // Held while creating, so a directory created for two files at once is recorded once
+        let mut created_dirs = self
+            .created_dirs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        let missing_dirs = parent
+            .ancestors()
+            .take_while(|x| !x.as_os_str().is_empty() && symlink_metadata(x).is_err())
+            .map(Path::to_path_buf)
+            .collect::<Vec<PathBuf>>();
+
+        create_dir_all(parent)?;
+        created_dirs.extend(missing_dirs.into_iter().rev());
+
+        Ok(())
+    }
+
+    /// Writes `content` to `path` with the given attributes and flushes it to disk before it replaces the current file.
+    pub fn write(
+        &self,
+        path: &Path,
+        content: &[u8],
+        attributes: &FileAttributes,
+        backup: bool,
+    ) -> io::Result<()> {
+        self.replace_with(path, backup, |temp| {
+            // Only the owner can read the content until it has its final owner and mode
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(temp)?;
+            file.write_all(content)?;
+            attributes.set(temp)?;
+            file.sync_all()
+        })
+    }
+
+    /// Replaces `path` with a copy of `file`, which keeps its mode. Symlinks are copied as symlinks.
+    pub fn copy(&self, file: &Path, path: &Path, backup: bool) -> io::Result<()> {
+        self.replace_with(path, backup, |temp| save_original(file, temp))
+    }
+
+    pub fn symlink(&self, source: &Path, path: &Path, backup: bool) -> io::Result<()> {
+        self.replace_with(path, backup, |temp| symlink(source, temp))
+    }
+
+    pub fn hard_link(&self, source: &Path, path: &Path, backup: bool) -> io::Result<()> {
+        self.replace_with(path, backup, |temp| hard_link(source, temp))
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    pub fn created_dirs(&self) -> Vec<PathBuf> {
+        self.created_dirs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Removes the previous versions of all replaced files or moves them into the backup.
+    pub fn commit(self, backup: &mut Backup) {
+        for change in self
+            .changes
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+        {
+            let Some(original) = change.original else {
+                continue;
+            };
+
+            if !change.backup {
+                let _ = remove_file(original);
+                continue;
+            }
+
+            match backup.store(&change.path, &original) {
+                Ok(backup_path) => println!(
+                    "INFO: Moved the previous `{:#?}` to the backup at `{:#?}`.",
+                    change.path, backup_path
+                ),
+                Err(e) => println!(
+                    "WARNING: Failed to back up `{:#?}`, because of the following error:\n{}\nThe previous version was kept at `{:#?}`.",
+                    change.path, e, original
+                ),
+            }
+        }
+    }
+
+    /// Restores the previous versions of all replaced files and removes created files and directories, newest first.
+    pub fn rollback(self) {
+        let changes = self
+            .changes
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        for change in changes.into_iter().rev() {
+            let result = match &change.original {
+                Some(original) => rename(original, &change.path),
+                None => remove_file(&change.path),
+            };
+
+            if let Err(e) = result {
+                eprintln!(
+                    "ERROR: Failed to roll back `{:#?}`, because of the following error:\n{}",
+                    change.path, e
+                );
+            }
+        }
+
+        let created_dirs = self
+            .created_dirs
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        for dir in created_dirs.into_iter().rev() {
+            let _ = remove_dir(dir);
+        }
+    }
+}
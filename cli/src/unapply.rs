@@ -0,0 +1,132 @@
+use std::{
+    fs::{read_dir, remove_dir, remove_file, symlink_metadata},
+    path::Path,
+    process::exit,
+};
+
+use clap::ArgMatches;
+
+use crate::{
+    apply::{link_state, LinkState},
+    backup::{backup_path, latest_backup_of, move_file, prune_backup},
+    selection::Selection,
+    state::{file_hash, StateFile},
+    utils::{data_dir, get_config_file_content},
+};
+
+pub fn unapply(matches: &ArgMatches) {
+    let selection = Selection::from_matches(matches);
+
+    let config_struct = get_config_file_content();
+    let mut state = StateFile::read();
+    let data_dir = data_dir();
+
+    let mut failed = false;
+    let mut removed_count = 0;
+
+    for linked_file in &config_struct.linked_files() {
+        let Some(destination) = &linked_file.destination else {
+            continue;
+        };
+
+        if !selection.matches(linked_file) {
+            continue;
+        }
+
+        let Some(applied_file) = state.get(destination) else {
+            continue;
+        };
+
+        let destination = applied_file.destination.clone();
+
+        if symlink_metadata(&destination).is_ok() {
+            // Only files that still hold exactly what DotMan deployed are removed
+            let unchanged = if linked_file.strategy.is_copy() {
+                applied_file.hash.is_some() && file_hash(&destination) == applied_file.hash
+            } else {
+                matches!(
+                    link_state(&linked_file.source, &destination, linked_file.strategy),
+                    LinkState::Correct
+                )
+            };
+
+            if !unchanged {
+                println!(
+                    "WARNING: `{:#?}` was edited since it was last applied. Skipping...",
+                    destination
+                );
+                continue;
+            }
+
+            if let Err(e) = remove_file(&destination) {
+                eprintln!(
+                    "ERROR: Failed to remove `{:#?}`, because of the following error:\n{}",
+                    destination, e
+                );
+                failed = true;
+                continue;
+            }
+
+            println!("INFO: Removed `{:#?}`.", destination);
+            removed_count += 1;
+        }
+
+        // The file DotMan replaced takes its place again
+        if let Some(backup_dir) = latest_backup_of(&data_dir, &destination) {
+            match move_file(&backup_path(&backup_dir, &destination), &destination) {
+                Ok(_) => println!("INFO: Restored `{:#?}` from its backup.", destination),
+                Err(e) => {
+                    eprintln!(
+                        "ERROR: Failed to restore `{:#?}` from its backup, because of the following error:\n{}",
+                        destination, e
+                    );
+                    failed = true;
+                }
+            }
+
+            prune_backup(&backup_dir);
+        }
+
+        state.remove(&destination);
+    }
+
+    prune_created_dirs(&mut state);
+    state.write();
+
+    if removed_count == 0 && !failed {
+        println!("INFO: There were no deployed files to remove.");
+    }
+
+    if failed {
+        exit(1);
+    }
+}
+
+fn is_empty_dir(path: &Path) -> bool {
+    read_dir(path).is_ok_and(|mut x| x.next().is_none())
+}
+
+/// Removes the empty directories created by `apply`, deepest first.
+fn prune_created_dirs(state: &mut StateFile) {
+    state
+        .created_dirs
+        .sort_by_key(|x| std::cmp::Reverse(x.components().count()));
+
+    state.created_dirs.retain(|dir| {
+        if !dir.exists() {
+            return false;
+        }
+
+        if !is_empty_dir(dir) {
+            return true;
+        }
+
+        match remove_dir(dir) {
+            Ok(_) => {
+                println!("INFO: Removed empty directory `{:#?}`.", dir);
+                false
+            }
+            Err(_) => true,
+        }
+    });
+}
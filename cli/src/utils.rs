@@ -1,27 +1,16 @@
 use std::{
-    collections::HashSet,
-    fs::{File, OpenOptions},
-    hash::Hash,
-    io::{Read, Write},
-    path::PathBuf,
+    env::var,
+    fs::{create_dir_all, File, OpenOptions},
+    io::{stdin, stdout, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+    process::{exit, Command},
 };
 
-use walkdir::WalkDir;
+pub use template_engine::config::config_file_path;
+use template_engine::paths;
 
 use crate::config::ConfigFile;
 
-pub fn config_file_path() -> PathBuf {
-    let base_dirs =
-        directories::BaseDirs::new().expect("ERROR: Could not find home or base directory");
-
-    let config_dir = base_dirs.config_local_dir();
-
-    let mut config_file_path = config_dir.to_path_buf();
-    config_file_path.push("dotman.toml");
-
-    config_file_path
-}
-
 pub fn get_config_file_content() -> ConfigFile {
     let mut file = File::open(config_file_path()).expect("ERROR: Failed to open config file.");
     let mut content = String::new();
@@ -31,35 +20,50 @@ pub fn get_config_file_content() -> ConfigFile {
     toml::from_str(content.as_str()).expect("ERROR: Failed to parse config file.")
 }
 
+/// The dotman data directory, which is created if it does not exist yet.
+pub fn data_dir() -> PathBuf {
+    let base_dirs = match directories::BaseDirs::new() {
+        Some(x) => x,
+        None => {
+            eprintln!("ERROR: Failed to find local data directory.");
+            exit(1);
+        }
+    };
+
+    let config_data_dir = base_dirs.data_dir().join("dotman");
+
+    if !config_data_dir.exists() {
+        match create_dir_all(&config_data_dir) {
+            Ok(_) => println!(
+                "WARNING: Data directory was not found. Created new directory at `{:#?}`",
+                config_data_dir
+            ),
+
+            Err(e) => {
+                eprintln!("ERROR: Data directory was not found. Failed to create a new directory at `{:#?}` with error `{:#?}`.", config_data_dir, e);
+                exit(1);
+            }
+        }
+    }
+
+    config_data_dir
+}
+
 pub fn home_dir() -> PathBuf {
     get_config_file_content().home_path
 }
 
 pub fn flat_file_array(path: PathBuf) -> Vec<PathBuf> {
-    let mut ret = Vec::new();
-
-    if path.is_dir() {
-        for entry in WalkDir::new(path) {
-            let entry = match entry {
-                Ok(entry) => entry,
-                Err(e) => {
-                    eprintln!(
-                        "WARING: Failed to add file with error `{}. Skipping...`",
-                        e.to_string()
-                    );
-                    continue;
-                }
-            };
-
-            if entry.path().is_dir() {
-                ret.extend(flat_file_array(entry.path().to_path_buf()).into_iter());
-            } else {
-                ret.push(entry.path().to_path_buf());
-            }
-        }
+    if !path.is_dir() {
+        return Vec::new();
     }
 
-    ret
+    walk_files(&path, &home_dir())
+}
+
+/// All files below `root` that are not ignored, warning about the ones that can not be read.
+pub fn walk_files(root: &Path, home_path: &Path) -> Vec<PathBuf> {
+    paths::walk_files(root, home_path, |e| println!("WARNING: {}", e))
 }
 
 pub fn write_config(content: &ConfigFile) {
@@ -76,11 +80,51 @@ pub fn write_config(content: &ConfigFile) {
         .expect("ERROR: Failed to write config to disk.");
 }
 
-pub fn has_unique_elements<T>(iter: T) -> bool
-where
-    T: IntoIterator,
-    T::Item: Eq + Hash,
-{
-    let mut uniq = HashSet::new();
-    iter.into_iter().all(move |x| uniq.insert(x))
+/// Asks a yes or no question on the terminal. Defaults to no, also if stdin is not a terminal.
+pub fn confirm(question: &str) -> bool {
+    if !stdin().is_terminal() {
+        return false;
+    }
+
+    print!("{} [y/N] ", question);
+    let _ = stdout().flush();
+
+    let mut answer = String::new();
+    if stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Opens a file in `$VISUAL` or `$EDITOR`, falling back to `vi`, and waits for the editor to exit.
+pub fn open_editor(path: &Path) -> bool {
+    let editor = var("VISUAL")
+        .or_else(|_| var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+
+    // The editor variable may contain arguments like `code --wait`
+    let mut parts = editor.split_whitespace();
+    let Some(program) = parts.next() else {
+        return false;
+    };
+
+    match Command::new(program).args(parts).arg(path).status() {
+        Ok(status) => status.success(),
+        Err(e) => {
+            eprintln!(
+                "ERROR: Failed to start the editor `{}`, because of the following error:\n{}",
+                editor, e
+            );
+            false
+        }
+    }
+}
+
+/// The content as text, or `None` if it is binary, because it is not valid UTF-8 or contains NUL bytes like most binary formats.
+pub fn as_text(content: &[u8]) -> Option<String> {
+    match content.contains(&0) {
+        true => None,
+        false => String::from_utf8(content.to_vec()).ok(),
+    }
 }
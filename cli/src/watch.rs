@@ -0,0 +1,345 @@
+use std::{
+    collections::HashSet,
+    env::current_exe,
+    ffi::OsStr,
+    fs::{read, read_to_string},
+    path::{absolute, Path, PathBuf},
+    process::{exit, Command},
+    sync::mpsc::channel,
+    time::Duration,
+};
+
+use clap::ArgMatches;
+use notify_debouncer_full::{
+    new_debouncer,
+    notify::{EventKind, RecursiveMode},
+    DebouncedEvent,
+};
+use template_engine::analysis::variable_references;
+
+use crate::{
+    config::ConfigFile,
+    utils::{as_text, config_file_path, get_config_file_content},
+};
+
+pub fn watch(matches: &ArgMatches) {
+    // Safe unwraps: The arguments have default values
+    let debounce = *matches.get_one::<u64>("debounce").unwrap();
+    let on_conflict = matches.get_one::<String>("on_conflict").unwrap();
+    let no_hooks = matches.get_flag("no_hooks");
+
+    let config_struct = get_config_file_content();
+    let home_path = normalize(&config_struct.home_path);
+
+    let (sender, receiver) = channel();
+
+    let mut debouncer = match new_debouncer(Duration::from_millis(debounce), None, sender) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!(
+                "ERROR: Failed to start watching for changes, because of the following error:\n{}",
+                e
+            );
+            exit(1);
+        }
+    };
+
+    let config_file_path = normalize(&config_file_path());
+    // `apply` writes the config as well, so only changes of its content count
+    let mut config_content = read_to_string(&config_file_path).unwrap_or_default();
+
+    let mut watching = watched_dirs(&config_struct, &home_path, &config_file_path);
+
+    for (path, mode) in &watching {
+        if let Err(e) = debouncer.watch(path, *mode) {
+            eprintln!(
+                "ERROR: Failed to watch `{:#?}`, because of the following error:\n{}",
+                path, e
+            );
+            exit(1);
+        }
+    }
+
+    println!(
+        "INFO: Watching `{:#?}` for changes. Press Ctrl-C to stop.",
+        home_path
+    );
+
+    for result in receiver {
+        let events = match result {
+            Ok(x) => x,
+            Err(errors) => {
+                for e in errors {
+                    println!(
+                        "WARNING: Failed to receive file changes, because of the following error:\n{}",
+                        e
+                    );
+                }
+                continue;
+            }
+        };
+
+        let changed_paths = changed_paths(events);
+        if changed_paths.is_empty() {
+            continue;
+        }
+
+        // Picks up files and settings added to the config since the last change
+        let config_struct = get_config_file_content();
+
+        // Sources and data files outside of the dotman home may have been added or created
+        let current_dirs = watched_dirs(&config_struct, &home_path, &config_file_path);
+
+        for (path, _) in watching.iter().filter(|x| !current_dirs.contains(x)) {
+            let _ = debouncer.unwatch(path);
+        }
+
+        watching.retain(|x| current_dirs.contains(x));
+
+        for (path, mode) in current_dirs {
+            if watching.contains(&(path.clone(), mode)) {
+                continue;
+            }
+
+            match debouncer.watch(&path, mode) {
+                Ok(_) => watching.push((path, mode)),
+                Err(e) => println!(
+                    "WARNING: Failed to watch `{:#?}`, because of the following error:\n{}\nSkipping...",
+                    path, e
+                ),
+            }
+        }
+
+        let mut config_changed = false;
+        if changed_paths.contains(&config_file_path) {
+            let content = read_to_string(&config_file_path).unwrap_or_default();
+            config_changed = content != config_content;
+            config_content = content;
+        }
+
+        let sources = match config_changed {
+            true => {
+                println!("INFO: The config changed. Applying all files...");
+                Vec::new()
+            }
+            false => {
+                let sources = affected_sources(&config_struct, &changed_paths);
+                if sources.is_empty() {
+                    continue;
+                }
+
+                sources
+            }
+        };
+
+        apply(&sources, on_conflict, no_hooks);
+    }
+}
+
+/// The absolute path, with symlinks resolved if it exists.
+fn normalize(path: &Path) -> PathBuf {
+    path.canonicalize()
+        .or_else(|_| absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// The changed files, without the files git keeps in the dotman home.
+/// Reading a file is reported as well, which would make every `apply` trigger the next one.
+fn changed_paths(events: Vec<DebouncedEvent>) -> HashSet<PathBuf> {
+    events
+        .iter()
+        .filter(|x| !matches!(x.kind, EventKind::Access(_)))
+        .flat_map(|x| &x.paths)
+        .map(|x| normalize(x))
+        .filter(|x| !x.components().any(|x| x.as_os_str() == OsStr::new(".git")))
+        .collect()
+}
+
+/// The dotman home and the existing directories of the sources, data files and the config outside of it.
+/// Editors replace files instead of writing to them, so the directories containing them are watched.
+fn watched_dirs(
+    config_struct: &ConfigFile,
+    home_path: &Path,
+    config_file_path: &Path,
+) -> Vec<(PathBuf, RecursiveMode)> {
+    let mut watched_dirs = vec![(home_path.to_path_buf(), RecursiveMode::Recursive)];
+
+    for path in watched_paths(config_struct)
+        .into_iter()
+        .chain([config_file_path.to_path_buf()])
+        .filter(|x| !x.starts_with(home_path))
+        .filter_map(|x| x.parent().map(Path::to_path_buf))
+        .filter(|x| x.is_dir())
+    {
+        if !watched_dirs.iter().any(|(x, _)| x == &path) {
+            watched_dirs.push((path, RecursiveMode::NonRecursive));
+        }
+    }
+
+    watched_dirs
+}
+
+/// The sources and data files of the config, which may live outside of the dotman home.
+fn watched_paths(config_struct: &ConfigFile) -> Vec<PathBuf> {
+    config_struct
+        .linked_files()
+        .iter()
+        .map(|x| normalize(&x.source))
+        .chain(
+            config_struct
+                .data_files_iter()
+                .map(|(_, path)| normalize(&path)),
+        )
+        .collect()
+}
+
+/// The variables used and assigned in a source or template, or `None` if it can not be read as text.
+fn references(path: &Path, source: bool) -> Option<(HashSet<String>, HashSet<String>)> {
+    let content = read(path).ok().and_then(|x| as_text(&x))?;
+
+    let (assigned, used): (Vec<_>, Vec<_>) = variable_references(&content, source)
+        .into_iter()
+        .partition(|x| x.assignment);
+
+    Some((
+        used.into_iter().map(|x| x.identifier).collect(),
+        assigned.into_iter().map(|x| x.identifier).collect(),
+    ))
+}
+
+/// The sources of the deployed files that have to be rendered again after the given paths changed.
+/// A template is affected if it changed itself or uses a variable assigned in a changed source or data file,
+/// including variables assigned from other affected variables.
+pub fn affected_sources(
+    config_struct: &ConfigFile,
+    changed_paths: &HashSet<PathBuf>,
+) -> Vec<PathBuf> {
+    let linked_files = config_struct.linked_files();
+
+    let variable_sources = linked_files
+        .iter()
+        .filter(|x| x.is_variable_source())
+        .map(|x| (normalize(&x.source), references(&x.source, true)))
+        .collect::<Vec<_>>();
+
+    // Variables whose value may have changed
+    let mut changed_variables = config_struct
+        .data_files_iter()
+        .filter(|(_, path)| changed_paths.contains(&normalize(path)))
+        .filter_map(|(name, _)| name)
+        .collect::<HashSet<String>>();
+
+    let mut all_variables = false;
+
+    for (path, references) in &variable_sources {
+        if !changed_paths.contains(path) {
+            continue;
+        }
+
+        match references {
+            Some((_, assigned)) => changed_variables.extend(assigned.iter().cloned()),
+            // The variables of removed sources are unknown, so every template may be affected
+            None => all_variables = true,
+        }
+    }
+
+    // Sources that were removed from the config do not show up here, but their variables are gone as well
+    if !all_variables
+        && changed_paths.iter().any(|x| {
+            x.extension() == Some(OsStr::new("te"))
+                && !x.exists()
+                && !variable_sources.iter().any(|(path, _)| path == x)
+        })
+    {
+        all_variables = true;
+    }
+
+    // Follows assignments that use changed variables until no new variable is affected
+    loop {
+        let count = changed_variables.len();
+
+        for (used, assigned) in variable_sources.iter().filter_map(|(_, x)| x.as_ref()) {
+            if !used.is_disjoint(&changed_variables) {
+                changed_variables.extend(assigned.iter().cloned());
+            }
+        }
+
+        if changed_variables.len() == count {
+            break;
+        }
+    }
+
+    let mut sources = Vec::new();
+
+    for linked_file in &linked_files {
+        if linked_file.destination.is_none() {
+            continue;
+        }
+
+        let source = normalize(&linked_file.source);
+
+        let affected = changed_paths.contains(&source)
+            || linked_file.condition.as_ref().is_some_and(|condition| {
+                all_variables
+                    || variable_references(condition, true)
+                        .iter()
+                        .any(|x| changed_variables.contains(&x.identifier))
+            })
+            || (linked_file.strategy.is_copy()
+                && (all_variables || !changed_variables.is_empty())
+                && references(&linked_file.source, false).is_some_and(|(used, _)| {
+                    (all_variables && !used.is_empty()) || !used.is_disjoint(&changed_variables)
+                }));
+
+        // `apply` selects files by the path as it is written in the config
+        let Ok(source) = absolute(&linked_file.source) else {
+            continue;
+        };
+
+        if affected && !sources.contains(&source) {
+            sources.push(source);
+        }
+    }
+
+    sources
+}
+
+/// Runs `apply` for the given sources, or for all files if there are none.
+/// A separate process keeps watching even if `apply` fails.
+fn apply(sources: &[PathBuf], on_conflict: &str, no_hooks: bool) {
+    let executable = match current_exe() {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!(
+                "ERROR: Failed to determine the path of dotman, because of the following error:\n{}",
+                e
+            );
+            exit(1);
+        }
+    };
+
+    for source in sources {
+        println!("INFO: Applying `{:#?}`...", source);
+    }
+
+    let mut command = Command::new(executable);
+    command.arg("apply").args(["--on-conflict", on_conflict]);
+
+    if no_hooks {
+        command.arg("--no-hooks");
+    }
+
+    command.arg("--").args(sources);
+
+    match command.status() {
+        Ok(status) if status.success() => (),
+        Ok(status) => println!(
+            "WARNING: `apply` exited with {}. Waiting for the next change...",
+            status
+        ),
+        Err(e) => eprintln!(
+            "ERROR: Failed to run `apply`, because of the following error:\n{}",
+            e
+        ),
+    }
+}
@@ -0,0 +1,93 @@
+use std::{
+    ffi::OsStr,
+    fs::read_to_string,
+    path::{Component, Path, PathBuf},
+};
+
+use serde::Deserialize;
+use template_engine::{
+    config::{config_file_path, DataFile},
+    paths::{expand_path, walk_files},
+};
+
+/// The parts of the dotman config the language server needs to resolve variables like `dotman apply` does.
+#[derive(Deserialize, Default)]
+pub struct ConfigFile {
+    pub home_path: PathBuf,
+    #[serde(default)]
+    pub data_files: Vec<DataFile>,
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub managed_paths: Vec<ManagedPath>,
+    /// The directory of the config file, which relative sources are resolved against
+    #[serde(skip)]
+    pub config_dir: PathBuf,
+}
+
+/// A managed file or directory. Only the paths are needed to find the sources of the variables.
+#[derive(Deserialize)]
+pub struct ManagedPath {
+    pub source: PathBuf,
+    pub destination: Option<PathBuf>,
+}
+
+/// Resolves the `..` and `.` components of an absolute path without following symlinks,
+/// so the path matches the one the editor opens the file with.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => (),
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+impl ConfigFile {
+    /// Reads the dotman config, falling back to an empty one if it is missing or invalid.
+    pub fn read() -> Self {
+        let config_file_path = config_file_path();
+
+        let mut config: Self = read_to_string(&config_file_path)
+            .ok()
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default();
+
+        if let Some(parent) = config_file_path.parent() {
+            config.config_dir = parent.to_path_buf();
+        }
+
+        config
+    }
+
+    /// The `.te` files managed without a destination, which define the variables of the templates like they do for `dotman apply`.
+    /// Managed directories are searched for them, leaving out the files ignored by `.dotmanignore`.
+    pub fn variable_sources(&self) -> Vec<PathBuf> {
+        self.managed_paths
+            .iter()
+            .filter(|x| x.destination.is_none())
+            .filter_map(|x| expand_path(&x.source, None).ok())
+            .map(|source| normalize(&self.config_dir.join(source)))
+            .flat_map(|source| match source.is_dir() {
+                true => walk_files(&source, &self.home_path, |_| ()),
+                false => vec![source],
+            })
+            .filter(|x| x.extension() == Some(OsStr::new("te")))
+            .collect()
+    }
+
+    /// Data files with their variable name and full path.
+    pub fn data_files(&self) -> Vec<(String, PathBuf)> {
+        self.data_files
+            .iter()
+            .filter_map(|x| Some((x.variable_name()?, x.full_path(&self.home_path))))
+            .collect()
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionResponse, CompletionTextEdit, Diagnostic,
+    DiagnosticSeverity, GotoDefinitionResponse, Hover, HoverContents, Location, MarkupContent,
+    MarkupKind, Position, Range, TextEdit, Url,
+};
+use template_engine::{
+    analysis::{check_file, variable_references, VariableReference},
+    config::BUILT_IN_VARIABLES,
+    TemplateEngine, FUNCTIONS,
+};
+
+use crate::workspace::{is_source, Workspace};
+
+const KEYWORDS: [&str; 6] = ["if", "for", "in", "end", "profile", "host"];
+
+fn range(line: usize, start: usize, end: usize) -> Range {
+    Range::new(
+        Position::new(line as u32, start as u32),
+        Position::new(line as u32, end as u32),
+    )
+}
+
+fn reference_at(content: &str, source: bool, position: Position) -> Option<VariableReference> {
+    let line = position.line as usize;
+    let character = position.character as usize;
+
+    variable_references(content, source)
+        .into_iter()
+        .find(|x| x.line == line && x.start <= character && character <= x.end)
+}
+
+/// Every assignment in the workspace sources and the given document.
+fn assignments(workspace: &Workspace, uri: &Url) -> Vec<(Url, VariableReference)> {
+    let mut files = workspace.source_files();
+
+    // Templates and sources that are not managed by dotman can use their own assignments as well
+    if !files.iter().any(|(x, _)| x == uri) {
+        if let Some(content) = workspace.document(uri) {
+            files.push((uri.clone(), content.clone()));
+        }
+    }
+
+    files
+        .into_iter()
+        .flat_map(|(file_uri, content)| {
+            variable_references(&content, is_source(&file_uri))
+                .into_iter()
+                .filter(|x| x.assignment)
+                .map(move |x| (file_uri.clone(), x))
+        })
+        .collect()
+}
+
+/// The names of all variables a document can use: assignments, data files and the built-in variables.
+fn known_variables(workspace: &Workspace, uri: &Url, engine: &TemplateEngine) -> BTreeSet<String> {
+    assignments(workspace, uri)
+        .into_iter()
+        .map(|(_, x)| x.identifier)
+        .chain(workspace.data_files().into_iter().map(|(name, _)| name))
+        .chain(engine.variables().keys().cloned())
+        .chain(BUILT_IN_VARIABLES.iter().map(|x| x.to_string()))
+        .collect()
+}
+
+pub fn diagnostics(workspace: &Workspace, uri: &Url) -> Vec<Diagnostic> {
+    let Some(content) = workspace.document(uri) else {
+        return Vec::new();
+    };
+    let source = is_source(uri);
+
+    let mut diagnostics = check_file(content, source)
+        .into_iter()
+        .map(|x| Diagnostic {
+            range: range(
+                x.line,
+                0,
+                content.lines().nth(x.line).unwrap_or("").chars().count(),
+            ),
+            severity: Some(DiagnosticSeverity::ERROR),
+            source: Some("dotman".to_string()),
+            message: x.message,
+            ..Default::default()
+        })
+        .collect::<Vec<Diagnostic>>();
+
+    let known = known_variables(workspace, uri, workspace.engine());
+
+    diagnostics.extend(
+        variable_references(content, source)
+            .into_iter()
+            .filter(|x| !x.assignment && !known.contains(&x.identifier))
+            .map(|x| Diagnostic {
+                range: range(x.line, x.start, x.end),
+                severity: Some(DiagnosticSeverity::WARNING),
+                source: Some("dotman".to_string()),
+                message: format!("Variable `${}` is never assigned.", x.identifier),
+                ..Default::default()
+            }),
+    );
+
+    diagnostics
+}
+
+pub fn definition(
+    workspace: &Workspace,
+    uri: &Url,
+    position: Position,
+) -> Option<GotoDefinitionResponse> {
+    let content = workspace.document(uri)?;
+    let reference = reference_at(content, is_source(uri), position)?;
+
+    let mut locations = assignments(workspace, uri)
+        .into_iter()
+        .filter(|(_, x)| x.identifier == reference.identifier)
+        .map(|(file_uri, x)| Location::new(file_uri, range(x.line, x.start, x.end)))
+        .collect::<Vec<Location>>();
+
+    locations.extend(
+        workspace
+            .data_files()
+            .into_iter()
+            .filter(|(name, _)| *name == reference.identifier)
+            .filter_map(|(_, path)| Url::from_file_path(path).ok())
+            .map(|file_uri| Location::new(file_uri, range(0, 0, 0))),
+    );
+
+    if locations.is_empty() {
+        None
+    } else {
+        Some(GotoDefinitionResponse::Array(locations))
+    }
+}
+
+pub fn hover(workspace: &Workspace, uri: &Url, position: Position) -> Option<Hover> {
+    let content = workspace.document(uri)?;
+    let reference = reference_at(content, is_source(uri), position)?;
+
+    let engine = workspace.engine();
+
+    let value = match engine.variables().get(&reference.identifier) {
+        Some(value) => format!("```\n${} = {}\n```", reference.identifier, value),
+        None => format!("`${}` has no value on this host.", reference.identifier),
+    };
+
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        }),
+        range: Some(range(reference.line, reference.start, reference.end)),
+    })
+}
+
+pub fn completion(
+    workspace: &Workspace,
+    uri: &Url,
+    position: Position,
+) -> Option<CompletionResponse> {
+    let content = workspace.document(uri)?;
+    let line = content.lines().nth(position.line as usize).unwrap_or("");
+    let before_cursor = line
+        .chars()
+        .take(position.character as usize)
+        .collect::<Vec<char>>();
+
+    let mut word_start = before_cursor.len();
+    while word_start > 0
+        && (before_cursor[word_start - 1].is_alphanumeric()
+            || before_cursor[word_start - 1] == '_'
+            || before_cursor[word_start - 1] == '-')
+    {
+        word_start -= 1;
+    }
+
+    let is_variable = word_start > 0 && before_cursor[word_start - 1] == '$';
+    if is_variable {
+        word_start -= 1;
+    }
+
+    let edit_range = range(
+        position.line as usize,
+        word_start,
+        position.character as usize,
+    );
+
+    let engine = workspace.engine();
+
+    let variables = known_variables(workspace, uri, engine)
+        .into_iter()
+        .map(|identifier| {
+            let value = engine.variables().get(&identifier).map(|x| x.to_string());
+            (identifier, value)
+        })
+        .collect::<BTreeMap<String, Option<String>>>();
+
+    let mut items = variables
+        .into_iter()
+        .map(|(identifier, value)| CompletionItem {
+            label: format!("${}", identifier),
+            kind: Some(CompletionItemKind::VARIABLE),
+            detail: value,
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(
+                edit_range,
+                format!("${}", identifier),
+            ))),
+            ..Default::default()
+        })
+        .collect::<Vec<CompletionItem>>();
+
+    if !is_variable {
+        items.extend(KEYWORDS.iter().map(|keyword| CompletionItem {
+            label: keyword.to_string(),
+            kind: Some(CompletionItemKind::KEYWORD),
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(
+                edit_range,
+                keyword.to_string(),
+            ))),
+            ..Default::default()
+        }));
+
+        items.extend(FUNCTIONS.iter().map(|function| CompletionItem {
+            label: function.to_string(),
+            kind: Some(CompletionItemKind::FUNCTION),
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(
+                edit_range,
+                format!("{}(", function),
+            ))),
+            ..Default::default()
+        }));
+    }
+
+    Some(CompletionResponse::Array(items))
+}
@@ -0,0 +1,194 @@
+use std::{error::Error, process::exit};
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+        Notification as NotificationTrait, PublishDiagnostics,
+    },
+    request::{Completion, GotoDefinition, HoverRequest, Request as RequestTrait},
+    CompletionOptions, Diagnostic, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, HoverProviderCapability, OneOf, PublishDiagnosticsParams,
+    ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+
+mod config;
+mod handlers;
+mod workspace;
+
+use workspace::Workspace;
+
+fn main() {
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec!["$".to_string()]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    // Safe unwrap: Data is known
+    let capabilities = serde_json::to_value(capabilities).unwrap();
+
+    // The sources are taken from the dotman config, so the workspace folders of the editor are not needed
+    if let Err(e) = connection.initialize(capabilities) {
+        eprintln!(
+            "ERROR: Failed to initialize the language server with the following error:\n{}",
+            e
+        );
+        exit(1);
+    }
+
+    let mut workspace = Workspace::new();
+
+    if let Err(e) = main_loop(&connection, &mut workspace) {
+        eprintln!("ERROR: The language server stopped unexpectedly:\n{}", e);
+        exit(1);
+    }
+
+    drop(connection);
+
+    if let Err(e) = io_threads.join() {
+        eprintln!("ERROR: Failed to shut down the language server:\n{}", e);
+        exit(1);
+    }
+}
+
+fn main_loop(connection: &Connection, workspace: &mut Workspace) -> Result<(), Box<dyn Error>> {
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+
+                let response = handle_request(workspace, request);
+                connection.sender.send(Message::Response(response))?;
+            }
+            Message::Notification(notification) => {
+                handle_notification(connection, workspace, notification)?;
+            }
+            Message::Response(_) => (),
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_request(workspace: &Workspace, request: Request) -> Response {
+    let id = request.id.clone();
+
+    let result = match request.method.as_str() {
+        GotoDefinition::METHOD => request
+            .extract::<<GotoDefinition as RequestTrait>::Params>(GotoDefinition::METHOD)
+            .map(|(_, params)| {
+                let position = params.text_document_position_params;
+                serde_json::to_value(handlers::definition(
+                    workspace,
+                    &position.text_document.uri,
+                    position.position,
+                ))
+            }),
+        HoverRequest::METHOD => request
+            .extract::<<HoverRequest as RequestTrait>::Params>(HoverRequest::METHOD)
+            .map(|(_, params)| {
+                let position = params.text_document_position_params;
+                serde_json::to_value(handlers::hover(
+                    workspace,
+                    &position.text_document.uri,
+                    position.position,
+                ))
+            }),
+        Completion::METHOD => request
+            .extract::<<Completion as RequestTrait>::Params>(Completion::METHOD)
+            .map(|(_, params)| {
+                let position = params.text_document_position;
+                serde_json::to_value(handlers::completion(
+                    workspace,
+                    &position.text_document.uri,
+                    position.position,
+                ))
+            }),
+        method => {
+            return Response::new_err(
+                id,
+                ErrorCode::MethodNotFound as i32,
+                format!("Unsupported request `{}`", method),
+            )
+        }
+    };
+
+    match result {
+        Ok(Ok(value)) => Response::new_ok(id, value),
+        Ok(Err(e)) => Response::new_err(id, ErrorCode::InternalError as i32, e.to_string()),
+        Err(e) => Response::new_err(id, ErrorCode::InvalidParams as i32, e.to_string()),
+    }
+}
+
+fn handle_notification(
+    connection: &Connection,
+    workspace: &mut Workspace,
+    notification: Notification,
+) -> Result<(), Box<dyn Error>> {
+    match notification.method.as_str() {
+        DidOpenTextDocument::METHOD => {
+            let params: DidOpenTextDocumentParams = serde_json::from_value(notification.params)?;
+            workspace.open(params.text_document.uri, params.text_document.text);
+        }
+        DidChangeTextDocument::METHOD => {
+            let mut params: DidChangeTextDocumentParams =
+                serde_json::from_value(notification.params)?;
+            // Full document sync: the last change holds the whole new content
+            match params.content_changes.pop() {
+                Some(change) => workspace.open(params.text_document.uri, change.text),
+                None => return Ok(()),
+            }
+        }
+        DidCloseTextDocument::METHOD => {
+            let params: DidCloseTextDocumentParams = serde_json::from_value(notification.params)?;
+            workspace.close(&params.text_document.uri);
+            send_diagnostics(connection, params.text_document.uri, Vec::new())?;
+        }
+        _ => return Ok(()),
+    }
+
+    publish_diagnostics(connection, workspace)
+}
+
+fn send_diagnostics(
+    connection: &Connection,
+    uri: Url,
+    diagnostics: Vec<Diagnostic>,
+) -> Result<(), Box<dyn Error>> {
+    let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+
+    connection
+        .sender
+        .send(Message::Notification(Notification::new(
+            PublishDiagnostics::METHOD.to_string(),
+            params,
+        )))?;
+
+    Ok(())
+}
+
+/// Assignments in one file affect the diagnostics of every other file, so all open documents are checked.
+fn publish_diagnostics(
+    connection: &Connection,
+    workspace: &Workspace,
+) -> Result<(), Box<dyn Error>> {
+    for uri in workspace.open_documents() {
+        send_diagnostics(
+            connection,
+            uri.clone(),
+            handlers::diagnostics(workspace, uri),
+        )?;
+    }
+
+    Ok(())
+}
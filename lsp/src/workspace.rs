@@ -0,0 +1,123 @@
+use std::{
+    collections::HashMap,
+    fs::read_to_string,
+    path::{absolute, PathBuf},
+};
+
+use lsp_types::Url;
+use template_engine::TemplateEngine;
+
+use crate::config::ConfigFile;
+
+/// Files opened in the editor and the `.te` sources managed by dotman.
+pub struct Workspace {
+    documents: HashMap<Url, String>,
+    config: ConfigFile,
+    /// The variables of all sources, evaluated again whenever a source changes
+    engine: TemplateEngine,
+    /// The last content of each source file that evaluated without an error
+    evaluated_sources: HashMap<Url, String>,
+}
+
+/// Source files hold variable assignments, everything else is treated as a template.
+pub fn is_source(uri: &Url) -> bool {
+    uri.path().ends_with(".te")
+}
+
+impl Workspace {
+    pub fn new() -> Self {
+        let mut workspace = Self {
+            documents: HashMap::new(),
+            config: ConfigFile::read(),
+            engine: TemplateEngine::default(),
+            evaluated_sources: HashMap::new(),
+        };
+        workspace.evaluate();
+
+        workspace
+    }
+
+    pub fn open(&mut self, uri: Url, content: String) {
+        let source = is_source(&uri);
+        self.documents.insert(uri, content);
+
+        if source {
+            self.evaluate();
+        }
+    }
+
+    pub fn close(&mut self, uri: &Url) {
+        self.documents.remove(uri);
+
+        // Unsaved changes of a closed source are discarded
+        if is_source(uri) {
+            self.evaluate();
+        }
+    }
+
+    pub fn document(&self, uri: &Url) -> Option<&String> {
+        self.documents.get(uri)
+    }
+
+    pub fn open_documents(&self) -> impl Iterator<Item = &Url> {
+        self.documents.keys()
+    }
+
+    /// The source files managed by dotman in the order `dotman apply` evaluates them.
+    /// The editor content is used for open files, so unsaved changes are taken into account.
+    pub fn source_files(&self) -> Vec<(Url, String)> {
+        self.config
+            .variable_sources()
+            .into_iter()
+            .filter_map(|path| {
+                let uri = Url::from_file_path(absolute(&path).ok()?).ok()?;
+
+                let content = match self.documents.get(&uri) {
+                    Some(content) => content.clone(),
+                    None => read_to_string(&path).ok()?,
+                };
+
+                Some((uri, content))
+            })
+            .collect()
+    }
+
+    /// Variable names and paths of the data files registered in the dotman config.
+    pub fn data_files(&self) -> Vec<(String, PathBuf)> {
+        self.config.data_files()
+    }
+
+    /// The variables of all source files for the current host.
+    pub fn engine(&self) -> &TemplateEngine {
+        &self.engine
+    }
+
+    /// Evaluates every source file to resolve the variables for the current host.
+    /// A file that fails to evaluate, e.g. while a line is being typed, contributes the
+    /// variables of its last content that evaluated instead.
+    fn evaluate(&mut self) {
+        let mut engine = TemplateEngine::for_host(self.config.profile.as_deref());
+
+        for data_file in &self.config.data_files {
+            let _ = engine.set_data_file(data_file, &self.config.home_path);
+        }
+
+        for (uri, content) in self.source_files() {
+            let mut evaluated = engine.clone();
+
+            match evaluated.evaluate_source_file(content.clone()) {
+                Ok(_) => {
+                    engine = evaluated;
+                    self.evaluated_sources.insert(uri, content);
+                }
+                Err(_) => {
+                    if let Some(previous) = self.evaluated_sources.get(&uri) {
+                        let _ = engine.evaluate_source_file(previous.clone());
+                    }
+                }
+            }
+        }
+
+        self.engine = engine;
+    }
+}
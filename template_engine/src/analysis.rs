@@ -0,0 +1,131 @@
+use crate::{expression_lines, line_expression, Expression};
+
+/// A line that could not be parsed into an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub message: String,
+}
+
+/// An occurrence of `$identifier` in a file.
+/// `start` and `end` are character offsets into the line and include the leading `$`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariableReference {
+    pub identifier: String,
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+    pub assignment: bool,
+}
+
+/// Parses every expression of a source (`source == true`) or template file without evaluating it
+/// and returns the errors encountered, including blocks without a matching `end`.
+pub fn check_file(content: &str, source: bool) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut open_blocks = Vec::new();
+
+    for (line, index) in expression_lines(content, source) {
+        match Expression::from_string(line_expression(line, source).to_string()) {
+            Ok(Expression::ForLoop(..) | Expression::LayerBlock(..)) => open_blocks.push(index),
+            Ok(Expression::End) => {
+                if open_blocks.pop().is_none() {
+                    diagnostics.push(Diagnostic {
+                        line: index,
+                        message: "`end` without a matching `for`, `profile` or `host`.".to_string(),
+                    });
+                }
+            }
+            Ok(_) => (),
+            Err(e) => diagnostics.push(Diagnostic {
+                line: index,
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    for index in open_blocks {
+        diagnostics.push(Diagnostic {
+            line: index,
+            message: "The block is never closed with `end`.".to_string(),
+        });
+    }
+
+    diagnostics.sort_by_key(|x| x.line);
+
+    diagnostics
+}
+
+/// Whether a template file contains any `{{ }}` expression lines, i.e. its rendered output differs from the file itself.
+pub fn is_templated(content: &str) -> bool {
+    !expression_lines(content, false).is_empty()
+}
+
+/// Returns all variables used or assigned in the expressions of a source or template file.
+pub fn variable_references(content: &str, source: bool) -> Vec<VariableReference> {
+    expression_lines(content, source)
+        .into_iter()
+        .flat_map(|(line, index)| line_references(line, index))
+        .collect()
+}
+
+fn is_identifier_char(char: char) -> bool {
+    char.is_alphanumeric() || char == '_' || char == '-'
+}
+
+fn line_references(line: &str, line_number: usize) -> Vec<VariableReference> {
+    let chars = line.chars().collect::<Vec<char>>();
+    let mut references = Vec::new();
+    let mut in_string = false;
+    let mut escape = false;
+    let mut index = 0;
+
+    while index < chars.len() {
+        let char = chars[index];
+
+        if escape {
+            escape = false;
+        } else if char == '\\' {
+            escape = true;
+        } else if char == '"' {
+            in_string = !in_string;
+        } else if !in_string && char == '/' && chars.get(index + 1) == Some(&'/') {
+            break;
+        } else if !in_string && char == '$' {
+            let mut end = index + 1;
+            while end < chars.len() && is_identifier_char(chars[end]) {
+                end += 1;
+            }
+
+            if end > index + 1 {
+                let mut next = end;
+                while chars.get(next) == Some(&' ') {
+                    next += 1;
+                }
+
+                // The variable of a loop is assigned by the loop
+                let before = chars[..index].iter().collect::<String>();
+                let loop_variable = before.trim_end().strip_suffix("for").is_some_and(|x| {
+                    x.chars()
+                        .last()
+                        .is_none_or(|x| !is_identifier_char(x) && x != '$')
+                });
+
+                references.push(VariableReference {
+                    identifier: chars[index + 1..end].iter().collect(),
+                    line: line_number,
+                    start: index,
+                    end,
+                    assignment: loop_variable
+                        || (chars.get(next) == Some(&'=') && chars.get(next + 1) != Some(&'=')),
+                });
+
+                index = end;
+                continue;
+            }
+        }
+
+        index += 1;
+    }
+
+    references
+}
@@ -0,0 +1,70 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{data::load_data_file, TemplateEngine};
+
+/// Variables DotMan sets itself. `$profile` is only set if a profile is active.
+pub const BUILT_IN_VARIABLES: [&str; 3] = ["hostname", "os", "profile"];
+
+/// The path of the dotman config, which is shared by the cli and the language server.
+pub fn config_file_path() -> PathBuf {
+    let base_dirs =
+        directories::BaseDirs::new().expect("ERROR: Could not find home or base directory");
+
+    base_dirs.config_local_dir().join("dotman.toml")
+}
+
+/// A TOML, JSON or YAML file whose content is available to templates as `$name`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DataFile {
+    pub path: PathBuf,
+    pub name: Option<String>,
+}
+
+impl DataFile {
+    /// The variable name defaults to the file name without its extension.
+    pub fn variable_name(&self) -> Option<String> {
+        match &self.name {
+            Some(name) => Some(name.clone()),
+            None => self
+                .path
+                .file_stem()
+                .map(|x| x.to_string_lossy().to_string()),
+        }
+    }
+
+    /// Data file paths are relative to the dotman home unless they are absolute.
+    pub fn full_path(&self, home_path: &Path) -> PathBuf {
+        home_path.join(&self.path)
+    }
+}
+
+impl TemplateEngine {
+    /// An engine with the hostname and operating system of this machine as `$hostname` and `$os`
+    /// and the given profile activated, like `dotman apply` uses it.
+    pub fn for_host(profile: Option<&str>) -> Self {
+        let mut engine = TemplateEngine::default();
+
+        engine.set_hostname(gethostname::gethostname().to_string_lossy());
+        engine.set_variable("os", std::env::consts::OS.to_string().into());
+        if let Some(profile) = profile {
+            engine.set_profile(profile);
+        }
+
+        engine
+    }
+
+    /// Assigns the content of a data file to its variable.
+    pub fn set_data_file(&mut self, data_file: &DataFile, home_path: &Path) -> Result<()> {
+        let name = data_file
+            .variable_name()
+            .ok_or_else(|| anyhow!("Could not determine a variable name for the data file"))?;
+
+        let data = load_data_file(&data_file.full_path(home_path))?;
+        self.set_variable(name, data);
+
+        Ok(())
+    }
+}
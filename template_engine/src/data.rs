@@ -0,0 +1,109 @@
+use std::{fs::read_to_string, path::Path};
+
+use anyhow::{anyhow, Result};
+
+use crate::Variable;
+
+// Data files can hold numbers, booleans and null values, which are all treated as strings by the engine.
+
+impl From<toml::Value> for Variable {
+    fn from(value: toml::Value) -> Self {
+        match value {
+            toml::Value::String(str) => Variable::String(str),
+            toml::Value::Integer(int) => Variable::String(int.to_string()),
+            toml::Value::Float(float) => Variable::String(float.to_string()),
+            toml::Value::Boolean(bool) => Variable::String(bool.to_string()),
+            toml::Value::Datetime(datetime) => Variable::String(datetime.to_string()),
+            toml::Value::Array(array) => {
+                Variable::List(array.into_iter().map(Into::into).collect())
+            }
+            toml::Value::Table(table) => Variable::Map(
+                table
+                    .into_iter()
+                    .map(|(key, value)| (key, value.into()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<serde_json::Value> for Variable {
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => Variable::String(String::new()),
+            serde_json::Value::Bool(bool) => Variable::String(bool.to_string()),
+            serde_json::Value::Number(number) => Variable::String(number.to_string()),
+            serde_json::Value::String(str) => Variable::String(str),
+            serde_json::Value::Array(array) => {
+                Variable::List(array.into_iter().map(Into::into).collect())
+            }
+            serde_json::Value::Object(object) => Variable::Map(
+                object
+                    .into_iter()
+                    .map(|(key, value)| (key, value.into()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl TryFrom<serde_norway::Value> for Variable {
+    type Error = anyhow::Error;
+
+    fn try_from(value: serde_norway::Value) -> Result<Self> {
+        match value {
+            serde_norway::Value::Null => Ok(Variable::String(String::new())),
+            serde_norway::Value::Bool(bool) => Ok(Variable::String(bool.to_string())),
+            serde_norway::Value::Number(number) => Ok(Variable::String(number.to_string())),
+            serde_norway::Value::String(str) => Ok(Variable::String(str)),
+            serde_norway::Value::Sequence(sequence) => Ok(Variable::List(
+                sequence
+                    .into_iter()
+                    .map(TryInto::try_into)
+                    .collect::<Result<Vec<Variable>>>()?,
+            )),
+            serde_norway::Value::Mapping(mapping) => Ok(Variable::Map(
+                mapping
+                    .into_iter()
+                    .map(|(key, value)| {
+                        let key = match key {
+                            serde_norway::Value::String(str) => str,
+                            serde_norway::Value::Number(number) => number.to_string(),
+                            serde_norway::Value::Bool(bool) => bool.to_string(),
+                            _ => {
+                                return Err(anyhow!(
+                                    "Only strings, numbers and booleans are supported as keys."
+                                ))
+                            }
+                        };
+
+                        Ok((key, value.try_into()?))
+                    })
+                    .collect::<Result<_>>()?,
+            )),
+            serde_norway::Value::Tagged(tagged) => tagged.value.try_into(),
+        }
+    }
+}
+
+/// Parses the content of a data file in the given format (`toml`, `json`, `yaml` or `yml`).
+pub fn parse_data(content: &str, format: &str) -> Result<Variable> {
+    match format {
+        "toml" => Ok(toml::from_str::<toml::Value>(content)?.into()),
+        "json" => Ok(serde_json::from_str::<serde_json::Value>(content)?.into()),
+        "yaml" | "yml" => serde_norway::from_str::<serde_norway::Value>(content)?.try_into(),
+        _ => Err(anyhow!("Unsupported data file format `{}`", format)),
+    }
+}
+
+/// Reads a data file, using its extension to determine the format.
+pub fn load_data_file(path: &Path) -> Result<Variable> {
+    let format = path
+        .extension()
+        .and_then(|x| x.to_str())
+        .ok_or_else(|| anyhow!("Data file `{}` has no extension", path.display()))?;
+
+    let content = read_to_string(path)?;
+
+    parse_data(&content, format)
+}
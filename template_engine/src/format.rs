@@ -0,0 +1,217 @@
+use std::fmt::{self, Display};
+
+use anyhow::{anyhow, Result};
+
+use crate::{expression_lines, line_expression, Condition, Expression, Layer, Value, Variable};
+
+/// Writes a string literal, escaping quotes and backslashes so it is read back unchanged.
+fn write_string(f: &mut fmt::Formatter<'_>, str: &str) -> fmt::Result {
+    write!(f, "\"{}\"", str.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Writes the items separated by commas.
+fn write_list<T: Display>(f: &mut fmt::Formatter<'_>, items: &[T]) -> fmt::Result {
+    for (index, item) in items.iter().enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the entries of a map as `{ key = value, ... }`.
+fn write_map<'a, T: Display + 'a>(
+    f: &mut fmt::Formatter<'_>,
+    entries: impl Iterator<Item = (&'a String, &'a T)>,
+) -> fmt::Result {
+    write!(f, "{{")?;
+    for (index, (key, value)) in entries.enumerate() {
+        if index > 0 {
+            write!(f, ",")?;
+        }
+
+        if !key.is_empty()
+            && key
+                .chars()
+                .all(|x| x.is_alphanumeric() || x == '_' || x == '-')
+        {
+            write!(f, " {} = {}", key, value)?;
+        } else {
+            write!(f, " ")?;
+            write_string(f, key)?;
+            write!(f, " = {}", value)?;
+        }
+    }
+    write!(f, " }}")
+}
+
+impl Display for Variable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Variable::String(str) => write_string(f, str),
+            Variable::List(list) => {
+                write!(f, "[")?;
+                write_list(f, list)?;
+                write!(f, "]")
+            }
+            Variable::Map(map) => write_map(f, map.iter()),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Variable(var) => write!(f, "${}", var),
+            Value::Literal(lit) => write_string(f, lit),
+            Value::List(items) => {
+                write!(f, "[")?;
+                write_list(f, items)?;
+                write!(f, "]")
+            }
+            Value::Map(entries) => write_map(f, entries.iter().map(|(key, value)| (key, value))),
+            Value::Function(name, arguments) => {
+                write!(f, "{}(", name)?;
+                write_list(f, arguments)?;
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::IsEqual(val1, val2) => write!(f, "{} == {}", val1, val2),
+            Condition::IsNotEqual(val1, val2) => write!(f, "{} != {}", val1, val2),
+        }
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::VariableAssignment(identifier, value) => {
+                write!(f, "${} = {}", identifier, value)
+            }
+            Expression::VariableValue(identifier) => write!(f, "${}", identifier),
+            Expression::StringLiteral(lit) => write_string(f, lit),
+            Expression::FunctionCall(name, arguments) => {
+                write!(f, "{}", Value::Function(name.clone(), arguments.clone()))
+            }
+            Expression::IfStatement(condition, expression) => {
+                write!(f, "if {} {}", condition, expression)
+            }
+            Expression::ForLoop(identifier, value) => write!(f, "for ${} in {}", identifier, value),
+            Expression::LayerBlock(Layer::Profile, value) => write!(f, "profile {}", value),
+            Expression::LayerBlock(Layer::Host, value) => write!(f, "host {}", value),
+            // Assignments outside of `profile` and `host` blocks are in the default layer, which has no keyword
+            Expression::LayerBlock(Layer::Default, _) => {
+                unreachable!("Only `profile` and `host` blocks are parsed")
+            }
+            Expression::End => write!(f, "end"),
+            Expression::Comment => write!(f, "//"),
+        }
+    }
+}
+
+/// Splits a line into its code and the text of a trailing `//` comment.
+fn split_comment(line: &str) -> (&str, Option<&str>) {
+    let mut in_string = false;
+    let mut escape = false;
+    let mut previous_slash = false;
+
+    for (index, char) in line.char_indices() {
+        if escape {
+            escape = false;
+        } else if char == '\\' {
+            escape = true;
+        } else if char == '"' {
+            in_string = !in_string;
+        } else if char == '/' && !in_string {
+            if previous_slash {
+                return (&line[..index - 1], Some(&line[index + 1..]));
+            }
+            previous_slash = true;
+            continue;
+        }
+
+        previous_slash = false;
+    }
+
+    (line, None)
+}
+
+fn format_expression(expression: &str) -> Result<String> {
+    let (code, comment) = split_comment(expression);
+
+    // Parsed with its comment, because a trailing comment ends an expression like the end of the line does
+    let parsed = Expression::from_string(expression.to_string())?;
+    let canonical = parsed.to_string();
+    let canonical = canonical
+        .strip_suffix("//")
+        .unwrap_or(&canonical)
+        .trim_end();
+
+    let with_comment = |code: &str| {
+        let mut formatted = code.to_string();
+
+        if let Some(comment) = comment {
+            if !formatted.is_empty() {
+                formatted.push(' ');
+            }
+            formatted.push_str("//");
+
+            let comment = comment.trim();
+            if !comment.is_empty() {
+                formatted.push(' ');
+                formatted.push_str(comment);
+            }
+        }
+
+        formatted
+    };
+
+    let formatted = with_comment(canonical);
+
+    // Never change what a line evaluates to, even if the canonical form would
+    if Expression::from_string(formatted.clone()).ok() == Some(parsed) {
+        Ok(formatted)
+    } else {
+        Ok(with_comment(code.trim()))
+    }
+}
+
+/// Rewrites every expression of a source (`source == true`) or template file into its canonical form.
+/// Lines of a template file outside of `{{ }}` are left untouched.
+pub fn format_file(content: &str, source: bool) -> Result<String> {
+    let mut lines = content.lines().map(str::to_string).collect::<Vec<String>>();
+
+    for (line, index) in expression_lines(content, source) {
+        let expression = format_expression(line_expression(line, source))
+            .map_err(|e| anyhow!("Line {}: {}", index + 1, e))?;
+
+        lines[index] = if source {
+            expression
+        } else {
+            let indentation = &line[..line.len() - line.trim_start().len()];
+            format!("{}{{{{ {} }}}}", indentation, expression)
+        };
+    }
+
+    if source {
+        for line in lines.iter_mut() {
+            line.truncate(line.trim_end().len());
+        }
+    }
+
+    let mut formatted = lines.join("\n");
+
+    if content.ends_with('\n') {
+        formatted.push('\n');
+    }
+
+    Ok(formatted)
+}
@@ -0,0 +1,52 @@
+use anyhow::{anyhow, Result};
+
+use crate::Variable;
+
+/// Names of the functions that can be called in expressions.
+pub const FUNCTIONS: [&str; 2] = ["len", "keys"];
+
+pub(crate) fn call(name: &str, arguments: Vec<Variable>) -> Result<Variable> {
+    match name {
+        "len" => len(single_argument(name, arguments)?),
+        "keys" => keys(single_argument(name, arguments)?),
+        _ => Err(anyhow!("Unknown function `{}`", name)),
+    }
+}
+
+fn single_argument(name: &str, arguments: Vec<Variable>) -> Result<Variable> {
+    let count = arguments.len();
+    let mut arguments = arguments.into_iter();
+
+    match (arguments.next(), count) {
+        (Some(argument), 1) => Ok(argument),
+        _ => Err(anyhow!(
+            "Function `{}` takes 1 argument, but {} were given.",
+            name,
+            count
+        )),
+    }
+}
+
+/// Number of items of a list or map, or number of characters of a string.
+fn len(value: Variable) -> Result<Variable> {
+    let len = match value {
+        Variable::String(str) => str.chars().count(),
+        Variable::List(list) => list.len(),
+        Variable::Map(map) => map.len(),
+    };
+
+    Ok(Variable::String(len.to_string()))
+}
+
+/// Keys of a map in alphabetical order.
+fn keys(value: Variable) -> Result<Variable> {
+    match value {
+        Variable::Map(map) => Ok(Variable::List(
+            map.into_keys().map(Variable::String).collect(),
+        )),
+        _ => Err(anyhow!(
+            "Function `keys` expects a map, but a {} was given.",
+            value.kind()
+        )),
+    }
+}
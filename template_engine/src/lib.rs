@@ -1,7 +1,20 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    iter::Peekable,
+    str::Chars,
+};
 
 use anyhow::{anyhow, Result};
 
+pub mod analysis;
+pub mod config;
+pub mod data;
+pub mod format;
+mod functions;
+pub mod paths;
+
+pub use functions::FUNCTIONS;
+
 #[cfg(test)]
 mod tests;
 
@@ -9,6 +22,9 @@ mod tests;
 enum Value {
     Variable(String),
     Literal(String),
+    List(Vec<Value>),
+    Map(Vec<(String, Value)>),
+    Function(String, Vec<Value>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -16,20 +32,51 @@ enum Expression {
     VariableAssignment(String, Value),
     VariableValue(String),
     StringLiteral(String),
+    FunctionCall(String, Vec<Value>),
     IfStatement(Condition, Box<Expression>),
+    ForLoop(String, Value),
+    LayerBlock(Layer, Value),
+    End,
     Comment,
 }
 
+/// The layer a variable is assigned in. Assignments of a more specific layer take precedence,
+/// regardless of the order they are evaluated in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Layer {
+    #[default]
+    Default,
+    Profile,
+    Host,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 enum Token {
     Variable(String),
     String(String),
+    Identifier(String),
     If,
+    For,
+    In,
+    End,
     Assignment,
     Condition(bool),
+    Comma,
+    OpenParenthesis,
+    CloseParenthesis,
+    OpenBracket,
+    CloseBracket,
+    OpenBrace,
+    CloseBrace,
     Comment,
 }
 
+type TokenIter = Peekable<std::vec::IntoIter<Token>>;
+
+fn is_word_char(char: char) -> bool {
+    char.is_alphanumeric() || char == '_' || char == '-'
+}
+
 impl Expression {
     fn from_string(string: String) -> Result<Self> {
         let tokens = Self::tokenize_line(string)?;
@@ -38,8 +85,17 @@ impl Expression {
     }
 
     fn from_tokens(tokens: Vec<Token>) -> Result<Self> {
-        let mut token_iter = tokens.into_iter();
+        let mut token_iter = tokens.into_iter().peekable();
+
+        let expression = Self::parse_expression(&mut token_iter)?;
+
+        match token_iter.next() {
+            None | Some(Token::Comment) => Ok(expression),
+            Some(token) => Err(anyhow!("Unexpected token {:?} after expression.", token)),
+        }
+    }
 
+    fn parse_expression(token_iter: &mut TokenIter) -> Result<Self> {
         let next_token = match token_iter.next() {
             Some(t) => t,
             None => return Err(anyhow!("No tokens provided!")),
@@ -48,202 +104,305 @@ impl Expression {
         match next_token {
             Token::Comment => Ok(Self::Comment),
             Token::String(str) => Ok(Self::StringLiteral(str)),
-            Token::Variable(var) => Self::parse_variable(&mut token_iter, var),
-            Token::If => Self::parse_if_expression(&mut token_iter),
+            Token::Variable(var) => Self::parse_variable(token_iter, var),
+            Token::If => Self::parse_if_expression(token_iter),
+            Token::For => Self::parse_for_loop(token_iter),
+            Token::End => Ok(Self::End),
+            // `profile` and `host` are only keywords at the start of a line, so they stay usable as map keys
+            Token::Identifier(name) if name == "profile" || name == "host" => {
+                let layer = if name == "profile" {
+                    Layer::Profile
+                } else {
+                    Layer::Host
+                };
+
+                if token_iter.peek().is_none() {
+                    return Err(anyhow!("Expected a {} name after `{}`.", name, name));
+                }
+
+                Ok(Self::LayerBlock(layer, Self::parse_value(token_iter)?))
+            }
+            Token::Identifier(name) if token_iter.peek() == Some(&Token::OpenParenthesis) => {
+                Ok(Self::FunctionCall(name, Self::parse_arguments(token_iter)?))
+            }
             _ => Err(anyhow!("Unexpected token")),
         }
     }
 
-    fn parse_variable(
-        token_iter: &mut impl Iterator<Item = Token>,
-        variable_name: String,
-    ) -> Result<Self> {
-        let next_token = token_iter.next();
+    fn parse_variable(token_iter: &mut TokenIter, variable_name: String) -> Result<Self> {
+        match token_iter.peek() {
+            Some(Token::Assignment) => {
+                token_iter.next();
 
-        match next_token {
-            Some(assignment) if assignment == Token::Assignment => {
-                let next_token = token_iter.next();
-
-                match next_token {
-                    Some(Token::String(str)) => Ok(Self::VariableAssignment(
-                        variable_name,
-                        Value::Literal(str.to_string()),
+                match token_iter.peek() {
+                    None | Some(Token::Comment) => Err(anyhow!(
+                        "Expected string literal or variable name after assignment."
                     )),
-                    Some(Token::Variable(var2)) => Ok(Self::VariableAssignment(
+                    Some(_) => Ok(Self::VariableAssignment(
                         variable_name,
-                        Value::Variable(var2.to_string()),
-                    )),
-                    Some(_) | None => Err(anyhow!(
-                        "Expected string literal or variable name after assignment."
+                        Self::parse_value(token_iter)?,
                     )),
                 }
             }
+            None | Some(Token::Comment) => Ok(Self::VariableValue(variable_name)),
             Some(_) => Err(anyhow!("Expected assignment operator.")),
-            None => Ok(Self::VariableValue(variable_name)),
         }
     }
 
-    fn parse_if_expression(token_iter: &mut impl Iterator<Item = Token>) -> Result<Self> {
-        let val1 = match token_iter.next() {
-            Some(Token::String(str)) => Value::Literal(str),
-            Some(Token::Variable(var)) => Value::Variable(var),
-            Some(_) => return Err(anyhow!("Expected variable or string literal.")),
-            None => return Err(anyhow!("Missing tokens after `if`.")),
-        };
+    fn parse_if_expression(token_iter: &mut TokenIter) -> Result<Self> {
+        if token_iter.peek().is_none() {
+            return Err(anyhow!("Missing tokens after `if`."));
+        }
+
+        let condition = Self::parse_condition(token_iter)?;
+
+        let expression = Self::parse_expression(token_iter)?;
+
+        if matches!(
+            expression,
+            Self::ForLoop(..) | Self::LayerBlock(..) | Self::End
+        ) {
+            return Err(anyhow!(
+                "Blocks can not be used inside of an `if` expression."
+            ));
+        }
+
+        Ok(Self::IfStatement(condition, Box::new(expression)))
+    }
+
+    fn parse_condition(token_iter: &mut TokenIter) -> Result<Condition> {
+        let val1 = Self::parse_value(token_iter)?;
 
         let cond = match token_iter.next() {
             Some(Token::Condition(cond)) => cond,
             Some(_) | None => return Err(anyhow!("Expected comparison operator.")),
         };
 
-        let val2 = match token_iter.next() {
-            Some(Token::String(str)) => Value::Literal(str),
-            Some(Token::Variable(var)) => Value::Variable(var),
-            Some(_) => return Err(anyhow!("Expected variable or string literal.")),
-            None => return Err(anyhow!("Missing token after `if`.")),
-        };
+        if token_iter.peek().is_none() {
+            return Err(anyhow!("Missing value after comparison operator."));
+        }
+
+        let val2 = Self::parse_value(token_iter)?;
 
         if cond {
-            Ok(Self::IfStatement(
-                Condition::IsEqual(val1, val2),
-                Box::new(Self::from_tokens(token_iter.collect())?),
-            ))
+            Ok(Condition::IsEqual(val1, val2))
         } else {
-            Ok(Self::IfStatement(
-                Condition::IsNotEqual(val1, val2),
-                Box::new(Self::from_tokens(token_iter.collect())?),
-            ))
+            Ok(Condition::IsNotEqual(val1, val2))
         }
     }
 
-    pub(crate) fn tokenize_line(string: String) -> Result<Vec<Token>> {
-        let mut tokens = Vec::new();
-        let mut current_token = String::new();
-        let mut in_string = false;
-        let mut escape = false;
-        let mut variable = false;
-        let mut slash_seen = false;
-        let mut skip_space = false;
+    fn parse_for_loop(token_iter: &mut TokenIter) -> Result<Self> {
+        let variable_name = match token_iter.next() {
+            Some(Token::Variable(var)) if !var.contains(['.', '[']) => var,
+            _ => return Err(anyhow!("Expected variable name after `for`.")),
+        };
 
-        let mut char_iter = string.chars();
+        if token_iter.next() != Some(Token::In) {
+            return Err(anyhow!("Expected `in` after the loop variable."));
+        }
 
-        while let Some(char) = char_iter.next() {
-            if char == ' ' && skip_space {
-                #[cfg(test)]
-                println!("Space skipped!");
-                skip_space = false;
-                continue;
-            } else if skip_space {
-                skip_space = false;
-            }
+        Ok(Self::ForLoop(variable_name, Self::parse_value(token_iter)?))
+    }
 
-            if char == '"' && !escape {
-                if in_string {
-                    current_token.push(char);
-                    current_token = current_token.chars().filter(|c| *c != '"').collect();
-                    tokens.push(Token::String(current_token.clone()));
-                    current_token.clear();
-                    in_string = false;
-                    #[cfg(test)]
-                    println!("String end");
+    fn parse_value(token_iter: &mut TokenIter) -> Result<Value> {
+        match token_iter.next() {
+            Some(Token::String(str)) => Ok(Value::Literal(str)),
+            Some(Token::Variable(var)) => Ok(Value::Variable(var)),
+            Some(Token::Identifier(name)) => {
+                if token_iter.peek() == Some(&Token::OpenParenthesis) {
+                    Ok(Value::Function(name, Self::parse_arguments(token_iter)?))
+                } else if name.chars().all(|x| x.is_ascii_digit()) {
+                    Ok(Value::Literal(name))
                 } else {
-                    in_string = true;
-                    #[cfg(test)]
-                    println!("In string");
+                    Err(anyhow!(
+                        "Unknown identifier `{}`. Did you forget a `$` or quotes?",
+                        name
+                    ))
                 }
             }
+            Some(Token::OpenBracket) => {
+                let mut items = Vec::new();
 
-            if char == '$' && !escape && !in_string {
-                current_token.clear();
-                variable = true;
-                continue;
-            }
+                while token_iter.peek() != Some(&Token::CloseBracket) {
+                    items.push(Self::parse_value(token_iter)?);
 
-            if char == '/' && slash_seen && !escape && !in_string {
-                tokens.push(Token::Comment);
-                return Ok(tokens);
-            }
+                    if !Self::parse_separator(token_iter, &Token::CloseBracket)? {
+                        break;
+                    }
+                }
+                token_iter.next();
 
-            if char == '/' && !escape && !in_string {
-                slash_seen = true;
+                Ok(Value::List(items))
             }
+            Some(Token::OpenBrace) => {
+                let mut entries = Vec::new();
 
-            if char == '!' && !escape && !in_string {
-                if let Some(next_char) = char_iter.next() {
-                    match next_char {
-                        '=' => {
-                            tokens.push(Token::Condition(false));
-                            current_token.clear();
-                            skip_space = true;
-                            continue;
-                        }
-                        _ => {
-                            #[cfg(test)]
-                            println!("Char: {}\nNext char: {}", char, next_char);
-                            return Err(anyhow!(
-                                "Unknown expression encountered.\nDid you mean to do a comparison?"
-                            ));
-                        }
+                while token_iter.peek() != Some(&Token::CloseBrace) {
+                    let key = match token_iter.next() {
+                        Some(Token::Identifier(key)) | Some(Token::String(key)) => key,
+                        _ => return Err(anyhow!("Expected key of map entry.")),
+                    };
+
+                    if token_iter.next() != Some(Token::Assignment) {
+                        return Err(anyhow!("Expected `=` after the key `{}`.", key));
                     }
-                } else {
-                    panic!("Unforseen end of expression.\nDotMan does not support boolean expressions!");
-                }
-            }
 
-            if char == '=' && !escape && !in_string {
-                if let Some(next_char) = char_iter.next() {
-                    #[cfg(test)]
-                    println!("Char: {}, Next char: {}", char, next_char);
-                    match next_char {
-                        '=' => {
-                            tokens.push(Token::Condition(true));
-                            current_token.clear();
-                            skip_space = true;
-                            continue;
-                        }
-                        ' ' => {
-                            tokens.push(Token::Assignment);
-                            current_token.clear();
-                            continue;
-                        }
-                        _ => {
-                            tokens.push(Token::Assignment);
-                            current_token.clear();
-                            current_token.push(next_char);
-                            continue;
-                        }
+                    entries.push((key, Self::parse_value(token_iter)?));
+
+                    if !Self::parse_separator(token_iter, &Token::CloseBrace)? {
+                        break;
                     }
-                } else {
-                    return Err(anyhow!("Unforseen end of expression.\nDid you forget to enter a value for your assignment?"));
                 }
+                token_iter.next();
+
+                Ok(Value::Map(entries))
             }
+            Some(_) | None => Err(anyhow!(
+                "Expected string literal, variable, list, map or function call."
+            )),
+        }
+    }
+
+    fn parse_arguments(token_iter: &mut TokenIter) -> Result<Vec<Value>> {
+        // Skip the opening parenthesis
+        token_iter.next();
 
-            if escape {
-                escape = false;
+        let mut arguments = Vec::new();
+
+        while token_iter.peek() != Some(&Token::CloseParenthesis) {
+            arguments.push(Self::parse_value(token_iter)?);
+
+            if !Self::parse_separator(token_iter, &Token::CloseParenthesis)? {
+                break;
             }
+        }
+        token_iter.next();
 
-            if char == '\\' {
-                escape = true;
+        Ok(arguments)
+    }
+
+    /// Consumes the comma between two items and returns whether more items may follow.
+    /// Fails if the list is neither continued nor closed.
+    fn parse_separator(token_iter: &mut TokenIter, close: &Token) -> Result<bool> {
+        match token_iter.peek() {
+            Some(Token::Comma) => {
+                token_iter.next();
+                Ok(true)
             }
+            Some(token) if token == close => Ok(false),
+            _ => Err(anyhow!("Expected `,` or {:?}.", close)),
+        }
+    }
 
-            if current_token == "if" {
-                tokens.push(Token::If);
-                current_token.clear();
-                continue;
+    pub(crate) fn tokenize_line(string: String) -> Result<Vec<Token>> {
+        let mut tokens = Vec::new();
+
+        let mut char_iter = string.chars().peekable();
+
+        while let Some(char) = char_iter.next() {
+            let token = match char {
+                _ if char.is_whitespace() => continue,
+                '"' => Token::String(Self::tokenize_string(&mut char_iter)?),
+                '$' => Token::Variable(Self::tokenize_variable(&mut char_iter)?),
+                '/' if char_iter.peek() == Some(&'/') => {
+                    tokens.push(Token::Comment);
+                    return Ok(tokens);
+                }
+                '=' if char_iter.peek() == Some(&'=') => {
+                    char_iter.next();
+                    Token::Condition(true)
+                }
+                '=' => Token::Assignment,
+                '!' => match char_iter.next() {
+                    Some('=') => Token::Condition(false),
+                    Some(_) => {
+                        return Err(anyhow!(
+                            "Unknown expression encountered.\nDid you mean to do a comparison?"
+                        ))
+                    }
+                    None => {
+                        return Err(anyhow!(
+                            "Unforseen end of expression.\nDotMan does not support boolean expressions!"
+                        ))
+                    }
+                },
+                ',' => Token::Comma,
+                '(' => Token::OpenParenthesis,
+                ')' => Token::CloseParenthesis,
+                '[' => Token::OpenBracket,
+                ']' => Token::CloseBracket,
+                '{' => Token::OpenBrace,
+                '}' => Token::CloseBrace,
+                _ if is_word_char(char) => {
+                    let mut word = char.to_string();
+
+                    while let Some(next_char) = char_iter.next_if(|x| is_word_char(*x)) {
+                        word.push(next_char);
+                    }
+
+                    match word.as_str() {
+                        "if" => Token::If,
+                        "for" => Token::For,
+                        "in" => Token::In,
+                        "end" => Token::End,
+                        _ => Token::Identifier(word),
+                    }
+                }
+                _ => return Err(anyhow!("Unexpected character `{}`.", char)),
+            };
+
+            tokens.push(token);
+        }
+
+        Ok(tokens)
+    }
+
+    /// Reads a string up to its closing quote. `\"` and `\\` are unescaped, other backslashes are kept.
+    fn tokenize_string(char_iter: &mut Peekable<Chars>) -> Result<String> {
+        let mut string = String::new();
+
+        while let Some(char) = char_iter.next() {
+            match char {
+                '"' => return Ok(string),
+                '\\' => match char_iter.next_if(|x| *x == '"' || *x == '\\') {
+                    Some(escaped) => string.push(escaped),
+                    None => string.push(char),
+                },
+                _ => string.push(char),
             }
+        }
 
-            if char == ' ' && variable {
-                tokens.push(Token::Variable(current_token.clone()));
-                current_token.clear();
-                variable = false;
+        Err(anyhow!("String literal is missing its closing quote."))
+    }
+
+    /// Reads a variable path like `monitors[0].name`.
+    fn tokenize_variable(char_iter: &mut Peekable<Chars>) -> Result<String> {
+        let mut path = String::new();
+        let mut open_brackets = 0;
+
+        while let Some(char) = char_iter.next_if(|x| {
+            is_word_char(*x)
+                || *x == '.'
+                || *x == '['
+                || ((*x == ']' || *x == '$') && open_brackets > 0)
+        }) {
+            match char {
+                '[' => open_brackets += 1,
+                ']' => open_brackets -= 1,
+                _ => (),
             }
+            path.push(char);
+        }
 
-            current_token.push(char);
-            #[cfg(test)]
-            println!("Current token: {:#?}", current_token);
+        if path.is_empty() {
+            return Err(anyhow!("Expected variable name after `$`."));
         }
 
-        Ok(tokens)
+        if open_brackets > 0 {
+            return Err(anyhow!("Variable `{}` is missing a closing `]`.", path));
+        }
+
+        Ok(path)
     }
 }
 
@@ -253,173 +412,595 @@ enum Condition {
     IsNotEqual(Value, Value),
 }
 
+impl Condition {
+    /// Parses a standalone comparison like `$hostname == "desktop"`.
+    fn from_string(string: String) -> Result<Self> {
+        let tokens = Expression::tokenize_line(string)?;
+        let mut token_iter = tokens.into_iter().peekable();
+
+        if token_iter.peek().is_none() {
+            return Err(anyhow!("Missing condition."));
+        }
+
+        let condition = Expression::parse_condition(&mut token_iter)?;
+
+        match token_iter.next() {
+            None | Some(Token::Comment) => Ok(condition),
+            Some(token) => Err(anyhow!("Unexpected token {:?} after condition.", token)),
+        }
+    }
+}
+
+/// Returns the lines of a file that are evaluated as expressions together with their index.
+/// Every non-blank line of a source file is an expression, while template files only evaluate lines wrapped in `{{ }}`.
+pub(crate) fn expression_lines(content: &str, source: bool) -> Vec<(&str, usize)> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let line = line.trim();
+
+            if source {
+                !line.is_empty()
+            } else {
+                line.starts_with("{{") && line.ends_with("}}")
+            }
+        })
+        .map(|(index, line)| (line, index))
+        .collect()
+}
+
+/// Returns the expression of a line returned by `expression_lines`, stripping the `{{ }}` delimiters of template lines.
+pub(crate) fn line_expression(line: &str, source: bool) -> &str {
+    let line = line.trim();
+
+    if source {
+        return line;
+    }
+
+    line.strip_prefix("{{")
+        .and_then(|x| x.strip_suffix("}}"))
+        .unwrap_or(line)
+        .trim()
+}
+
+/// A line of a file prepared for evaluation.
+enum Line<'a> {
+    Text(&'a str),
+    Expression(Expression),
+}
+
+/// Parses the expression lines of a file. Other lines of a template file are kept as text.
+fn parse_lines(content: &str, source: bool) -> Result<Vec<(usize, Line<'_>)>> {
+    let mut expressions = expression_lines(content, source)
+        .into_iter()
+        .map(|(line, index)| {
+            match Expression::from_string(line_expression(line, source).to_string()) {
+                Ok(expression) => Ok((index, expression)),
+                Err(e) => Err(anyhow!("Line {}: {}", index + 1, e)),
+            }
+        })
+        .collect::<Result<HashMap<usize, Expression>>>()?;
+
+    Ok(content
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| match expressions.remove(&index) {
+            Some(expression) => Some((index, Line::Expression(expression))),
+            None if source => None,
+            None => Some((index, Line::Text(line))),
+        })
+        .collect())
+}
+
+/// Returns the position of the `end` closing the block at `start`.
+fn block_end(lines: &[(usize, Line)], start: usize) -> Result<usize> {
+    let mut depth = 0;
+
+    for (position, (_, line)) in lines.iter().enumerate().skip(start) {
+        match line {
+            Line::Expression(Expression::ForLoop(..) | Expression::LayerBlock(..)) => depth += 1,
+            Line::Expression(Expression::End) => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(position);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Err(anyhow!(
+        "Line {}: The block is never closed with `end`.",
+        lines[start].0 + 1
+    ))
+}
+
+/// The value of a variable. Lists and maps can be created by data files or list and map literals
+/// and are accessed with paths like `$theme.colors.bg` or `$monitors[0]`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Variable {
+    String(String),
+    List(Vec<Variable>),
+    Map(BTreeMap<String, Variable>),
+}
+
+impl Variable {
+    fn kind(&self) -> &'static str {
+        match self {
+            Variable::String(_) => "string",
+            Variable::List(_) => "list",
+            Variable::Map(_) => "map",
+        }
+    }
+
+    fn as_string(&self, path: &str) -> Result<String> {
+        match self {
+            Variable::String(str) => Ok(str.clone()),
+            _ => Err(anyhow!(
+                "Variable `{}` is a {} and can not be used as a string.",
+                path,
+                self.kind()
+            )),
+        }
+    }
+}
+
+impl From<&str> for Variable {
+    fn from(value: &str) -> Self {
+        Variable::String(value.to_string())
+    }
+}
+
+impl From<String> for Variable {
+    fn from(value: String) -> Self {
+        Variable::String(value)
+    }
+}
+
+impl PartialEq<str> for Variable {
+    fn eq(&self, other: &str) -> bool {
+        matches!(self, Variable::String(str) if str == other)
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct TemplateEngine {
-    pub(crate) variables: HashMap<String, String>,
+    pub(crate) variables: HashMap<String, Variable>,
     pub(crate) template_results: Vec<(Option<String>, usize)>,
+    /// Lines consumed by a block, which are replaced by the output of the block's first line
+    pub(crate) removed_lines: HashSet<usize>,
+    /// The layer each variable was last assigned in
+    pub(crate) variable_layers: HashMap<String, Layer>,
+    /// The layer assignments are currently made in
+    pub(crate) layer: Layer,
+    pub(crate) profile: Option<String>,
+    pub(crate) hostname: Option<String>,
 }
 
 impl TemplateEngine {
     /// Takes in source and template files (true = source && false = template) and returns their parsed and evaluted content
     pub fn parse_files(input: Vec<(String, bool)>) -> Result<Vec<String>> {
-        let mut results = Vec::new();
+        Self::default().evaluate_files(input)
+    }
 
-        let mut template_engine = Self::default();
+    /// Same as `parse_files`, but evaluates the files on top of the variables already known to the engine
+    pub fn evaluate_files(&mut self, input: Vec<(String, bool)>) -> Result<Vec<String>> {
+        let mut results = Vec::new();
 
         for (content, source) in input {
             if source {
-                template_engine.evaluate_source_file(content.clone())?;
+                self.evaluate_source_file(content.clone())?;
             } else {
-                template_engine.evaluate_template_file(content.clone())?;
+                self.evaluate_template_file(content.clone())?;
             }
 
-            results.push(template_engine.new_file_contents(&content));
+            results.push(self.new_file_contents(&content));
         }
 
         Ok(results)
     }
 
-    fn convert_value(&self, value: Value) -> Result<String> {
+    /// Renders a template on top of the variables known to the engine without changing them,
+    /// so templates can be rendered independently of each other, e.g. on multiple threads.
+    pub fn render_template(&self, content: &str) -> Result<String> {
+        let mut engine = self.clone();
+
+        engine.evaluate_template_file(content.to_string())?;
+
+        Ok(engine.new_file_contents(content))
+    }
+
+    /// All variables assigned so far, keyed by their identifier.
+    pub fn variables(&self) -> &HashMap<String, Variable> {
+        &self.variables
+    }
+
+    /// Assigns a variable from outside of a source file, e.g. the content of a data file.
+    pub fn set_variable<S: Into<String>>(&mut self, identifier: S, value: Variable) {
+        self.variables.insert(identifier.into(), value);
+    }
+
+    /// Activates the `profile` blocks of the given profile and makes it available as `$profile`.
+    pub fn set_profile<S: Into<String>>(&mut self, profile: S) {
+        let profile = profile.into();
+
+        self.set_variable("profile", profile.clone().into());
+        self.profile = Some(profile);
+    }
+
+    /// Activates the `host` blocks of the given hostname and makes it available as `$hostname`.
+    pub fn set_hostname<S: Into<String>>(&mut self, hostname: S) {
+        let hostname = hostname.into();
+
+        self.set_variable("hostname", hostname.clone().into());
+        self.hostname = Some(hostname);
+    }
+
+    /// Assigns a variable in the current layer, unless it was already assigned in a more specific one.
+    fn assign(&mut self, identifier: String, value: Variable) {
+        if self
+            .variable_layers
+            .get(&identifier)
+            .is_some_and(|x| *x > self.layer)
+        {
+            return;
+        }
+
+        self.variable_layers.insert(identifier.clone(), self.layer);
+        self.variables.insert(identifier, value);
+    }
+
+    /// Whether the lines of a `profile` or `host` block apply to this machine.
+    /// The block can name a single profile or host or a list of them.
+    fn layer_active(&self, layer: Layer, value: Value) -> Result<bool> {
+        let active = match layer {
+            Layer::Default => return Ok(true),
+            Layer::Profile => &self.profile,
+            Layer::Host => &self.hostname,
+        };
+
+        let Some(active) = active else {
+            return Ok(false);
+        };
+
+        match self.evaluate_value(value.clone())? {
+            Variable::String(str) => Ok(&str == active),
+            Variable::List(list) => Ok(list.iter().any(|x| x == active.as_str())),
+            Variable::Map(_) => Err(anyhow!(
+                "Expected a name or a list of names, but `{}` is a map.",
+                value
+            )),
+        }
+    }
+
+    /// Resolves a variable path like `theme.colors.bg` or `monitors[0].name`.
+    /// Map keys can be accessed with both notations, lists only with numeric indexes.
+    /// The key or index can also be taken from another variable, e.g. `$fonts[$kind]`.
+    fn lookup(&self, path: &str) -> Result<&Variable> {
+        let normalized_path = path.replace('[', ".").replace(']', "");
+        let mut segments = normalized_path.split('.');
+
+        // Safe unwrap: Split always returns at least one segment
+        let identifier = segments.next().unwrap();
+
+        let mut variable = match self.variables.get(identifier) {
+            Some(x) => x,
+            None => return Err(anyhow!("Unknown identifier `{}`", identifier)),
+        };
+
+        for segment in segments {
+            let segment = match segment.strip_prefix('$') {
+                Some(identifier) => self.lookup(identifier)?.as_string(identifier)?,
+                None => segment.to_string(),
+            };
+            let segment = segment.as_str();
+
+            variable = match variable {
+                Variable::Map(map) => match map.get(segment) {
+                    Some(x) => x,
+                    None => return Err(anyhow!("Variable `{}` has no key `{}`", path, segment)),
+                },
+                Variable::List(list) => {
+                    let index = match segment.parse::<usize>() {
+                        Ok(x) => x,
+                        Err(_) => {
+                            return Err(anyhow!(
+                            "Lists can only be indexed with numbers, but `{}` was used in `{}`.",
+                            segment,
+                            path
+                        ))
+                        }
+                    };
+
+                    match list.get(index) {
+                        Some(x) => x,
+                        None => {
+                            return Err(anyhow!(
+                                "Index {} is out of bounds in `{}`, which has {} items.",
+                                index,
+                                path,
+                                list.len()
+                            ))
+                        }
+                    }
+                }
+                Variable::String(_) => {
+                    return Err(anyhow!(
+                        "Can not access `{}` of variable `{}`, because it is a string.",
+                        segment,
+                        path
+                    ))
+                }
+            };
+        }
+
+        Ok(variable)
+    }
+
+    fn evaluate_value(&self, value: Value) -> Result<Variable> {
         match value {
-            Value::Literal(lit) => Ok(lit),
-            Value::Variable(var) => match self.variables.get(&var) {
-                Some(val) => Ok(val.to_string()),
-                None => Err(anyhow!(
-                    "Could not find value of variable with identifier `{}`",
-                    var
-                )),
-            },
+            Value::Literal(lit) => Ok(Variable::String(lit)),
+            Value::Variable(var) => Ok(self.lookup(&var)?.clone()),
+            Value::List(items) => Ok(Variable::List(
+                items
+                    .into_iter()
+                    .map(|x| self.evaluate_value(x))
+                    .collect::<Result<Vec<Variable>>>()?,
+            )),
+            Value::Map(entries) => Ok(Variable::Map(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| Ok((key, self.evaluate_value(value)?)))
+                    .collect::<Result<BTreeMap<String, Variable>>>()?,
+            )),
+            Value::Function(name, arguments) => {
+                let arguments = arguments
+                    .into_iter()
+                    .map(|x| self.evaluate_value(x))
+                    .collect::<Result<Vec<Variable>>>()?;
+
+                functions::call(&name, arguments)
+            }
+        }
+    }
+
+    fn is_true(&self, condition: Condition) -> Result<bool> {
+        match condition {
+            Condition::IsEqual(val1, val2) => {
+                Ok(self.evaluate_value(val1)? == self.evaluate_value(val2)?)
+            }
+            Condition::IsNotEqual(val1, val2) => {
+                Ok(self.evaluate_value(val1)? != self.evaluate_value(val2)?)
+            }
         }
     }
 
+    /// Evaluates a comparison like `$hostname == "desktop"` with the current variables,
+    /// using the same syntax as the condition of an `if` expression.
+    pub fn evaluate_condition(&self, condition: &str) -> Result<bool> {
+        self.is_true(Condition::from_string(condition.to_string())?)
+    }
+
     fn evaluate_expression(&mut self, expression: Expression) -> Result<Option<String>> {
         use Expression::*;
 
         match expression {
             VariableAssignment(identifier, value) => {
-                let _ = self
-                    .variables
-                    .insert(identifier, self.convert_value(value)?);
-                return Ok(None);
-            }
-            VariableValue(identifier) => match self.variables.get(&identifier) {
-                Some(x) => return Ok(Some(x.clone())),
-                None => return Err(anyhow!("Unknown identifier `{}`", identifier)),
-            },
-            StringLiteral(lit) => return Ok(Some(lit)),
-            IfStatement(condition, expression) => match condition {
-                Condition::IsEqual(val1, val2) => {
-                    if self.convert_value(val1)? == self.convert_value(val2)? {
-                        return self.evaluate_expression(*expression);
-                    } else {
-                        return Ok(None);
-                    }
+                if identifier.contains(['.', '[']) {
+                    return Err(anyhow!(
+                        "Can not assign to `{}`. Only plain variables can be assigned.",
+                        identifier
+                    ));
                 }
-                Condition::IsNotEqual(val1, val2) => {
-                    if self.convert_value(val1)? != self.convert_value(val2)? {
-                        return self.evaluate_expression(*expression);
-                    } else {
-                        return Ok(None);
-                    }
+
+                let value = self.evaluate_value(value)?;
+
+                self.assign(identifier, value);
+                Ok(None)
+            }
+            VariableValue(identifier) => {
+                Ok(Some(self.lookup(&identifier)?.as_string(&identifier)?))
+            }
+            StringLiteral(lit) => Ok(Some(lit)),
+            FunctionCall(name, arguments) => {
+                let value = Value::Function(name, arguments);
+                let label = value.to_string();
+
+                Ok(Some(self.evaluate_value(value)?.as_string(&label)?))
+            }
+            IfStatement(condition, expression) => {
+                if self.is_true(condition)? {
+                    self.evaluate_expression(*expression)
+                } else {
+                    Ok(None)
                 }
-            },
+            }
+            ForLoop(..) | LayerBlock(..) | End => {
+                Err(anyhow!("Blocks can only be evaluated as part of a file."))
+            }
             Comment => Ok(None),
         }
     }
 
-    pub fn evaluate_source_file(&mut self, content: String) -> Result<()> {
-        for (index, string) in content.lines().enumerate() {
-            let expression = Expression::from_string(string.to_string())?;
-            let template_result = (self.evaluate_expression(expression)?, index);
-            self.template_results.push(template_result);
-        }
+    /// Evaluates the given lines and returns their results. Text lines are returned as they are.
+    /// A block returns the output of its body as the result of its first line,
+    /// while the lines of its body are added to `removed_lines`.
+    fn evaluate_lines(&mut self, lines: &[(usize, Line)]) -> Result<Vec<(Option<String>, usize)>> {
+        let mut results = Vec::new();
+        let mut position = 0;
 
-        Ok(())
-    }
+        while position < lines.len() {
+            let (index, line) = &lines[position];
 
-    pub fn evaluate_template_file(&mut self, content: String) -> Result<()> {
-        let mut template_lines = vec![];
+            let expression = match line {
+                Line::Text(text) => {
+                    results.push((Some(text.to_string()), *index));
+                    position += 1;
+                    continue;
+                }
+                Line::Expression(expression) => expression.clone(),
+            };
+
+            match expression {
+                Expression::ForLoop(identifier, value) => {
+                    let end = block_end(lines, position)?;
+                    let body = &lines[position + 1..end];
+
+                    let items = match self.evaluate_value(value.clone()) {
+                        Ok(Variable::List(items)) => items,
+                        Ok(Variable::Map(map)) => map.into_keys().map(Variable::String).collect(),
+                        Ok(Variable::String(_)) => {
+                            return Err(anyhow!(
+                                "Line {}: Can not loop over `{}`, because it is a string.",
+                                index + 1,
+                                value
+                            ))
+                        }
+                        Err(e) => return Err(anyhow!("Line {}: {}", index + 1, e)),
+                    };
 
-        for (index, line) in content.lines().enumerate() {
-            let line = line.trim();
+                    // The loop variable only exists inside of the loop
+                    let shadowed = self.variables.remove(&identifier);
+
+                    let mut output = Vec::new();
+
+                    for item in items {
+                        self.variables.insert(identifier.clone(), item);
+
+                        for (result, _) in self.evaluate_lines(body)? {
+                            output.push(result.unwrap_or_default());
+                        }
+                    }
+
+                    self.variables.remove(&identifier);
+                    if let Some(shadowed) = shadowed {
+                        self.variables.insert(identifier, shadowed);
+                    }
+
+                    self.close_block(lines, position, end, output, &mut results);
+                    position = end + 1;
+                }
+                Expression::LayerBlock(layer, value) => {
+                    let end = block_end(lines, position)?;
+                    let body = &lines[position + 1..end];
+
+                    let active = self
+                        .layer_active(layer, value)
+                        .map_err(|e| anyhow!("Line {}: {}", index + 1, e))?;
+
+                    let mut output = Vec::new();
 
-            if line.starts_with("{{") && line.ends_with("}}") {
-                let line = line
-                    .chars()
-                    .filter(|x| *x != '{' || *x != '}')
-                    .collect::<String>();
+                    if active {
+                        let outer_layer = self.layer;
+                        self.layer = self.layer.max(layer);
 
-                let line = line.clone();
-                template_lines.push((line, index));
+                        let body_results = self.evaluate_lines(body);
+                        self.layer = outer_layer;
+
+                        for (result, _) in body_results? {
+                            output.push(result.unwrap_or_default());
+                        }
+                    }
+
+                    self.close_block(lines, position, end, output, &mut results);
+                    position = end + 1;
+                }
+                Expression::End => {
+                    return Err(anyhow!(
+                        "Line {}: `end` without a matching `for`, `profile` or `host`.",
+                        index + 1
+                    ))
+                }
+                expression => {
+                    let result = self
+                        .evaluate_expression(expression)
+                        .map_err(|e| anyhow!("Line {}: {}", index + 1, e))?;
+
+                    results.push((result, *index));
+                    position += 1;
+                }
             }
         }
 
-        template_lines = template_lines
-            .into_iter()
-            .map(|mut x| {
-                x.0 = x.0.trim().to_string();
-                x
-            })
-            .collect::<Vec<(String, usize)>>();
-
-        #[cfg(test)]
-        println!("Template lines:\n{:#?}\n", template_lines);
-
-        for (template_line, line_number) in template_lines.into_iter() {
-            let expression = Expression::from_string(template_line.to_string())?;
-            let template_result = (self.evaluate_expression(expression.clone())?, line_number);
-            #[cfg(test)]
-            println!(
-                "Line:\n{:#?}\nExpression:\n{:#?}\nResult:\n{:#?}",
-                template_line, expression, template_result
-            );
-            self.template_results.push(template_result);
+        Ok(results)
+    }
+
+    /// Replaces the first line of the block from `start` to `end` with its output and removes the other lines.
+    fn close_block(
+        &mut self,
+        lines: &[(usize, Line)],
+        start: usize,
+        end: usize,
+        output: Vec<String>,
+        results: &mut Vec<(Option<String>, usize)>,
+    ) {
+        self.removed_lines
+            .extend(lines[start + 1..=end].iter().map(|(index, _)| *index));
+
+        if output.is_empty() {
+            self.removed_lines.insert(lines[start].0);
+        } else {
+            results.push((Some(output.join("\n")), lines[start].0));
         }
+    }
+
+    pub fn evaluate_source_file(&mut self, content: String) -> Result<()> {
+        self.template_results.clear();
+        self.removed_lines.clear();
+
+        let lines = parse_lines(&content, true)?;
+
+        let results = self.evaluate_lines(&lines)?;
+        self.template_results.extend(results);
 
         Ok(())
     }
 
-    /// This function adds a newline at the end of the file
-    /// and is reliant on the fact that the template results are ordered
-    pub fn new_file_contents(&self, content: &String) -> String {
-        #[cfg(test)]
-        println!("Results:\n{:#?}", self.template_results);
+    pub fn evaluate_template_file(&mut self, content: String) -> Result<()> {
+        self.template_results.clear();
+        self.removed_lines.clear();
 
-        let mut results_index = 0;
+        let lines = parse_lines(&content, false)?;
 
-        let content_lines = content.lines().enumerate();
-        let mut result_file = String::new();
+        let text_lines = lines
+            .iter()
+            .filter(|(_, line)| matches!(line, Line::Text(_)))
+            .map(|(index, _)| *index)
+            .collect::<HashSet<usize>>();
 
-        for (index, mut line) in content_lines {
-            #[cfg(test)]
-            println!("Original line:\n{}", line);
+        // Text outside of loops stays untouched, so only expression results are kept
+        let results = self.evaluate_lines(&lines)?;
+        self.template_results.extend(
+            results
+                .into_iter()
+                .filter(|(_, index)| !text_lines.contains(index)),
+        );
 
-            if let Some(result) = self.template_results.get(results_index) {
-                let result_line_number = result.1;
+        Ok(())
+    }
 
-                println!("Result:\n{:#?}", result);
+    /// This function adds a newline at the end of the file.
+    /// Lines with a template result are replaced by it and lines consumed by a loop are removed.
+    pub fn new_file_contents(&self, content: &str) -> String {
+        let results = self
+            .template_results
+            .iter()
+            .map(|(result, index)| (*index, result))
+            .collect::<HashMap<usize, &Option<String>>>();
 
-                if result_line_number == index {
-                    line = match &self.template_results[results_index].0 {
-                        Some(ref str) => {
-                            results_index += 1;
-                            str
-                        }
-                        None => {
-                            results_index += 1;
-                            ""
-                        }
-                    };
-                }
-            } else {
-                #[cfg(test)]
-                println!("No remaining template results!");
+        let mut result_file = String::new();
 
-                result_file += format!("{}\n", line).as_str();
-                return result_file;
+        for (index, line) in content.lines().enumerate() {
+            if self.removed_lines.contains(&index) {
+                continue;
             }
-            #[cfg(test)]
-            println!("Result line:\n{}", line);
+
+            let line = match results.get(&index) {
+                Some(Some(str)) => str.as_str(),
+                Some(None) => "",
+                None => line,
+            };
 
             result_file += format!("{}\n", line).as_str();
         }
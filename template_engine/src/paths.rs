@@ -0,0 +1,183 @@
+use std::{
+    env,
+    ffi::OsStr,
+    path::{absolute, Path, PathBuf},
+};
+
+use ignore::{
+    gitignore::{Gitignore, GitignoreBuilder},
+    WalkBuilder,
+};
+
+use crate::{TemplateEngine, Variable};
+
+/// Name of the gitignore-style files that exclude files from managed directories.
+pub const IGNORE_FILE_NAME: &str = ".dotmanignore";
+
+/// The home directory of the current user.
+pub fn user_home() -> PathBuf {
+    directories::BaseDirs::new()
+        .expect("ERROR: Could not find home or base directory")
+        .home_dir()
+        .to_path_buf()
+}
+
+/// `$XDG_CONFIG_HOME`, which defaults to `~/.config` if it is not set.
+pub fn config_home() -> PathBuf {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|x| x.is_absolute())
+        .unwrap_or_else(|| user_home().join(".config"))
+}
+
+/// Expands a leading `~` as well as `$HOME`, `$XDG_CONFIG_HOME` and template variables like `$hostname` in a path of the config.
+/// `${name}` separates a variable from the characters following it. Template variables are only known if an engine is given.
+pub fn expand_path(
+    path: &Path,
+    template_engine: Option<&TemplateEngine>,
+) -> Result<PathBuf, String> {
+    let Some(mut rest) = path.to_str() else {
+        return Ok(path.to_path_buf());
+    };
+
+    let mut expanded = String::new();
+
+    if rest == "~" || rest.starts_with("~/") {
+        expanded += &user_home().to_string_lossy();
+        rest = &rest[1..];
+    }
+
+    while let Some(index) = rest.find('$') {
+        expanded += &rest[..index];
+        rest = &rest[index + 1..];
+
+        let (name, remaining) = match rest.strip_prefix('{') {
+            Some(braced) => match braced.split_once('}') {
+                Some(x) => x,
+                None => return Err("`${` is never closed with `}`".to_string()),
+            },
+            None => {
+                let end = rest
+                    .find(|x: char| !x.is_alphanumeric() && x != '_')
+                    .unwrap_or(rest.len());
+
+                rest.split_at(end)
+            }
+        };
+
+        if name.is_empty() {
+            return Err("`$` is not followed by a variable name".to_string());
+        }
+
+        expanded += &variable_value(name, template_engine)?;
+        rest = remaining;
+    }
+
+    expanded += rest;
+
+    Ok(PathBuf::from(expanded))
+}
+
+fn variable_value(name: &str, template_engine: Option<&TemplateEngine>) -> Result<String, String> {
+    match name {
+        "HOME" => return Ok(user_home().to_string_lossy().to_string()),
+        "XDG_CONFIG_HOME" => return Ok(config_home().to_string_lossy().to_string()),
+        _ => (),
+    }
+
+    match template_engine.and_then(|x| x.variables().get(name)) {
+        Some(Variable::String(value)) => Ok(value.clone()),
+        Some(_) => Err(format!(
+            "Variable `{}` is a list or map and can not be used in a path",
+            name
+        )),
+        None => Err(format!("Unknown variable `{}`", name)),
+    }
+}
+
+/// The form of a path written to the config, which works for other users and machines as well.
+/// Paths inside `$XDG_CONFIG_HOME` or the home directory are written relative to them,
+/// while paths that already start with `~` or contain a variable are kept as they are.
+pub fn portable_path(path: &Path) -> PathBuf {
+    let path_string = path.to_string_lossy();
+    if path_string.starts_with('~') || path_string.contains('$') {
+        return path.to_path_buf();
+    }
+
+    let Ok(path) = absolute(path) else {
+        return path.to_path_buf();
+    };
+
+    if let Ok(relative_path) = path.strip_prefix(config_home()) {
+        return Path::new("$XDG_CONFIG_HOME").join(relative_path);
+    }
+
+    if let Ok(relative_path) = path.strip_prefix(user_home()) {
+        return Path::new("~").join(relative_path);
+    }
+
+    path
+}
+
+/// All files below `root` in alphabetical order. `.git` directories and files matched by the
+/// `.dotmanignore` of the dotman home or by one in `root` or its subdirectories are left out.
+/// Ignore files and entries that can not be read are skipped and described to `on_error`.
+pub fn walk_files(root: &Path, home_path: &Path, mut on_error: impl FnMut(String)) -> Vec<PathBuf> {
+    // Patterns of the home ignore file are relative to the home, not to the current directory
+    let home_ignore_file = home_path.join(IGNORE_FILE_NAME);
+    let mut home_ignore = GitignoreBuilder::new(home_path);
+    if home_ignore_file.is_file() {
+        if let Some(e) = home_ignore.add(&home_ignore_file) {
+            on_error(format!(
+                "Failed to read ignore file `{:#?}`, because of the following error:\n{}",
+                home_ignore_file, e
+            ));
+        }
+    }
+    let home_ignore = match home_ignore.build() {
+        Ok(x) => x,
+        Err(e) => {
+            on_error(format!(
+                "Failed to read ignore file `{:#?}`, because of the following error:\n{}",
+                home_ignore_file, e
+            ));
+            Gitignore::empty()
+        }
+    };
+
+    let mut walk_builder = WalkBuilder::new(root);
+
+    walk_builder
+        .standard_filters(false)
+        .add_custom_ignore_filename(IGNORE_FILE_NAME)
+        .filter_entry(move |entry| {
+            let is_dir = entry.file_type().is_some_and(|x| x.is_dir());
+
+            entry.file_name() != OsStr::new(".git")
+                && !home_ignore.matched(entry.path(), is_dir).is_ignore()
+        })
+        .sort_by_file_name(|a, b| a.cmp(b));
+
+    let mut files = Vec::new();
+
+    for entry in walk_builder.build() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                on_error(format!(
+                    "Failed to read file with error `{}`. Skipping...",
+                    e
+                ));
+                continue;
+            }
+        };
+
+        if entry.file_type().is_some_and(|x| !x.is_dir())
+            && entry.file_name() != OsStr::new(IGNORE_FILE_NAME)
+        {
+            files.push(entry.into_path());
+        }
+    }
+
+    files
+}
@@ -0,0 +1,98 @@
+use crate::analysis::{
+    check_file, is_templated, variable_references, Diagnostic, VariableReference,
+};
+
+#[test]
+fn check_valid_source_file() {
+    let source_file =
+        "$host = \"desktop\"\n\n// Monitors\nif $host == \"desktop\" $monitor = \"DP-1\"";
+
+    assert_eq!(check_file(source_file, true), vec![]);
+}
+
+#[test]
+fn check_invalid_template_line() {
+    let template_file = "font_size = 12\n{{ $size = }}\n{{ $size }}";
+
+    let diagnostics = check_file(template_file, false);
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].line, 1);
+}
+
+#[test]
+fn check_unterminated_comparison() {
+    let source_file = "if $host !";
+
+    assert!(matches!(
+        check_file(source_file, true).as_slice(),
+        [Diagnostic { line: 0, .. }]
+    ));
+}
+
+#[test]
+fn find_references() {
+    let source_file = "if $host == \"laptop\" $font = $small_font // $ignored";
+
+    let references = variable_references(source_file, true);
+
+    let expected_references = vec![
+        VariableReference {
+            identifier: "host".to_string(),
+            line: 0,
+            start: 3,
+            end: 8,
+            assignment: false,
+        },
+        VariableReference {
+            identifier: "font".to_string(),
+            line: 0,
+            start: 21,
+            end: 26,
+            assignment: true,
+        },
+        VariableReference {
+            identifier: "small_font".to_string(),
+            line: 0,
+            start: 29,
+            end: 40,
+            assignment: false,
+        },
+    ];
+
+    assert_eq!(references, expected_references);
+}
+
+#[test]
+fn ignore_references_outside_template_lines() {
+    let template_file = "export PRICE=\"$5\"\n  {{ $shell }}";
+
+    let references = variable_references(template_file, false);
+
+    assert_eq!(references.len(), 1);
+    assert_eq!(references[0].identifier, "shell");
+    assert_eq!((references[0].line, references[0].start), (1, 5));
+}
+
+#[test]
+fn check_unmatched_loops() {
+    let template_file = "{{ for $monitor in $monitors }}\n{{ $monitor }}\n{{ end }}\n{{ end }}";
+
+    assert!(matches!(
+        check_file(template_file, false).as_slice(),
+        [Diagnostic { line: 3, .. }]
+    ));
+
+    let references = variable_references(template_file, false);
+
+    assert!(references[0].assignment);
+    assert!(!references[1].assignment);
+}
+
+#[test]
+fn detect_templated_files() {
+    assert!(is_templated("font_size = 12\n  {{ $font_size }}\n"));
+    assert!(!is_templated(
+        "font_size = 12\ncolor = \"{{ not a line }}\"\n"
+    ));
+}
@@ -0,0 +1,54 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+
+use crate::{data::parse_data, Variable};
+
+fn theme() -> Variable {
+    Variable::Map(BTreeMap::from([
+        (
+            "colors".to_string(),
+            Variable::Map(BTreeMap::from([
+                ("bg".to_string(), "#1e1e2e".into()),
+                ("fg".to_string(), "#cdd6f4".into()),
+            ])),
+        ),
+        (
+            "fonts".to_string(),
+            Variable::List(vec!["Iosevka".into(), "Noto Sans".into()]),
+        ),
+        ("size".to_string(), "12".into()),
+    ]))
+}
+
+#[test]
+fn parse_toml() -> Result<()> {
+    let content = "size = 12\nfonts = [\"Iosevka\", \"Noto Sans\"]\n\n[colors]\nbg = \"#1e1e2e\"\nfg = \"#cdd6f4\"\n";
+
+    assert_eq!(parse_data(content, "toml")?, theme());
+
+    Ok(())
+}
+
+#[test]
+fn parse_json() -> Result<()> {
+    let content = r##"{ "size": 12, "fonts": ["Iosevka", "Noto Sans"], "colors": { "bg": "#1e1e2e", "fg": "#cdd6f4" } }"##;
+
+    assert_eq!(parse_data(content, "json")?, theme());
+
+    Ok(())
+}
+
+#[test]
+fn parse_yaml() -> Result<()> {
+    let content = "size: 12\nfonts:\n  - Iosevka\n  - Noto Sans\ncolors:\n  bg: \"#1e1e2e\"\n  fg: \"#cdd6f4\"\n";
+
+    assert_eq!(parse_data(content, "yml")?, theme());
+
+    Ok(())
+}
+
+#[test]
+fn unsupported_format() {
+    assert!(parse_data("", "ini").is_err());
+}
@@ -1,6 +1,8 @@
+use std::collections::BTreeMap;
+
 use anyhow::Result;
 
-use crate::TemplateEngine;
+use crate::{TemplateEngine, Variable};
 
 #[test]
 fn add_variables() -> Result<()> {
@@ -44,3 +46,210 @@ fn conditional_assignment() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn dotted_variable_access() -> Result<()> {
+    let mut engine = TemplateEngine::default();
+
+    engine.set_variable(
+        "theme",
+        Variable::Map(BTreeMap::from([(
+            "colors".to_string(),
+            Variable::Map(BTreeMap::from([("bg".to_string(), "#1e1e2e".into())])),
+        )])),
+    );
+
+    let template_file = "background = \"#000000\"\n{{ $theme.colors.bg }}".to_string();
+
+    engine.evaluate_template_file(template_file.clone())?;
+
+    assert_eq!(
+        engine.new_file_contents(&template_file),
+        "background = \"#000000\"\n#1e1e2e\n"
+    );
+
+    let source_file = "$colors = $theme.colors\n$bg = $colors.bg".to_string();
+
+    engine.evaluate_source_file(source_file)?;
+
+    assert_eq!(engine.variables.get("bg").unwrap(), "#1e1e2e");
+
+    Ok(())
+}
+
+#[test]
+fn map_as_string() {
+    let mut engine = TemplateEngine::default();
+
+    engine.set_variable("theme", Variable::Map(BTreeMap::new()));
+
+    assert!(engine
+        .evaluate_template_file("{{ $theme }}".to_string())
+        .is_err());
+    assert!(engine
+        .evaluate_source_file("$missing = $theme.colors".to_string())
+        .is_err());
+}
+
+#[test]
+fn indexed_access_and_functions() -> Result<()> {
+    let mut engine = TemplateEngine::default();
+
+    let source_file = r#"$monitors = [{ name = "DP-1" }, { name = "HDMI-1" }]
+$second = $monitors[1].name
+$count = len($monitors)
+$names = keys({ b = "2", a = "1" })"#
+        .to_string();
+
+    engine.evaluate_source_file(source_file)?;
+
+    assert_eq!(engine.variables.get("second").unwrap(), "HDMI-1");
+    assert_eq!(engine.variables.get("count").unwrap(), "2");
+    assert_eq!(
+        engine.variables.get("names").unwrap(),
+        &Variable::List(vec!["a".into(), "b".into()])
+    );
+
+    assert!(engine
+        .evaluate_source_file("$missing = $monitors[2]".to_string())
+        .is_err());
+    assert!(engine
+        .evaluate_source_file("$missing = keys($second)".to_string())
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn template_loops() -> Result<()> {
+    let mut engine = TemplateEngine::default();
+
+    engine.set_variable(
+        "monitors",
+        Variable::List(vec!["DP-1".into(), "HDMI-1".into()]),
+    );
+    engine.set_variable("empty", Variable::List(vec![]));
+
+    let template_file = "[monitors]\n{{ for $monitor in $monitors }}\nmonitor = \n{{ $monitor }}\n{{ end }}\n{{ for $x in $empty }}\n{{ $x }}\n{{ end }}\ncount = \n{{ len($monitors) }}".to_string();
+
+    engine.evaluate_template_file(template_file.clone())?;
+
+    assert_eq!(
+        engine.new_file_contents(&template_file),
+        "[monitors]\nmonitor = \nDP-1\nmonitor = \nHDMI-1\ncount = \n2\n"
+    );
+    assert!(!engine.variables.contains_key("monitor"));
+
+    Ok(())
+}
+
+#[test]
+fn source_loops() -> Result<()> {
+    let mut engine = TemplateEngine::default();
+
+    let source_file = r#"$fonts = { mono = "Iosevka", sans = "Inter" }
+for $kind in $fonts
+if $kind == "sans" $sans = $fonts[$kind]
+end"#
+        .to_string();
+
+    engine.evaluate_source_file(source_file)?;
+
+    assert_eq!(engine.variables.get("sans").unwrap(), "Inter");
+
+    assert!(engine
+        .evaluate_source_file("for $x in $fonts".to_string())
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn layered_variables() -> Result<()> {
+    let mut engine = TemplateEngine::default();
+
+    engine.set_profile("work");
+    engine.set_hostname("desktop");
+
+    let source_file = r#"host ["desktop", "tower"]
+$font_size = "14"
+end
+profile "work"
+$font_size = "12"
+$email = "me@work.example"
+end
+profile "personal"
+$email = "me@home.example"
+end
+$font_size = "11"
+$email = "me@example.org""#
+        .to_string();
+
+    engine.evaluate_source_file(source_file)?;
+
+    assert_eq!(engine.variables.get("font_size").unwrap(), "14");
+    assert_eq!(engine.variables.get("email").unwrap(), "me@work.example");
+    assert_eq!(engine.variables.get("hostname").unwrap(), "desktop");
+
+    let template_file = "[user]\n{{ host \"laptop\" }}\n{{ $battery = \"BAT0\" }}\nbattery = true\n{{ end }}\n{{ profile \"work\" }}\nemail = work\n{{ end }}".to_string();
+
+    engine.evaluate_template_file(template_file.clone())?;
+
+    assert_eq!(
+        engine.new_file_contents(&template_file),
+        "[user]\nemail = work\n"
+    );
+    assert!(!engine.variables.contains_key("battery"));
+
+    Ok(())
+}
+
+#[test]
+fn layer_names_as_map_keys() -> Result<()> {
+    let mut engine = TemplateEngine::default();
+
+    engine
+        .evaluate_source_file(r#"$machine = { host = "desktop", profile = "work" }"#.to_string())?;
+
+    assert!(engine
+        .evaluate_source_file("host \"desktop\"\n$x = \"1\"".to_string())
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn standalone_conditions() -> Result<()> {
+    let mut engine = TemplateEngine::default();
+
+    engine.set_hostname("laptop");
+    engine.evaluate_source_file(r#"$gpu = { vendor = "nvidia" }"#.to_string())?;
+
+    assert!(engine.evaluate_condition(r#"$hostname == "laptop""#)?);
+    assert!(!engine.evaluate_condition(r#"$hostname != "laptop""#)?);
+    assert!(engine.evaluate_condition(r#"$gpu.vendor == "nvidia" // desktop only"#)?);
+
+    assert!(engine.evaluate_condition(r#"$hostname"#).is_err());
+    assert!(engine
+        .evaluate_condition(r#"$hostname == "a" "b""#)
+        .is_err());
+    assert!(engine.evaluate_condition(r#"$missing == "a""#).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn independent_templates() -> Result<()> {
+    let mut engine = TemplateEngine::default();
+
+    engine.evaluate_source_file(r#"$color = "red""#.to_string())?;
+
+    let first = engine.render_template("{{ $color = \"blue\" }}\n{{ $color }}")?;
+    let second = engine.render_template("{{ $color }}")?;
+
+    assert_eq!(first, "\nblue\n");
+    assert_eq!(second, "red\n");
+    assert_eq!(engine.variables.get("color").unwrap(), "red");
+
+    Ok(())
+}
@@ -70,3 +70,34 @@ fn if_expression() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn map_assignment() -> Result<()> {
+    let expression = Expression::from_string(
+        r#"$font = { family = "Iosevka", sizes = ["10", $size] }"#.to_string(),
+    )?;
+
+    let expected_expression = Expression::VariableAssignment(
+        "font".to_string(),
+        Value::Map(vec![
+            ("family".to_string(), Value::Literal("Iosevka".to_string())),
+            (
+                "sizes".to_string(),
+                Value::List(vec![
+                    Value::Literal("10".to_string()),
+                    Value::Variable("size".to_string()),
+                ]),
+            ),
+        ]),
+    );
+
+    assert_eq!(expression, expected_expression);
+
+    Ok(())
+}
+
+#[test]
+fn unclosed_list() {
+    assert!(Expression::from_string(r#"$list = ["a", "b""#.to_string()).is_err());
+    assert!(Expression::from_string(r#"$list = ["a" "b"]"#.to_string()).is_err());
+}
@@ -0,0 +1,89 @@
+use anyhow::Result;
+
+use crate::format::format_file;
+
+#[test]
+fn format_source_file() -> Result<()> {
+    let source_file =
+        "  $host =   \"desktop\"   \n\nif $host != \"laptop\"   $font = $big_font//Comment\n";
+
+    let formatted = format_file(source_file, true)?;
+
+    let expected_file =
+        "$host = \"desktop\"\n\nif $host != \"laptop\" $font = $big_font // Comment\n";
+
+    assert_eq!(formatted, expected_file);
+
+    Ok(())
+}
+
+#[test]
+fn format_template_lines_only() -> Result<()> {
+    let template_file = "font_family    Iosevka  \n    {{$font}}\n{{   $size = \"12\"   }}";
+
+    let formatted = format_file(template_file, false)?;
+
+    let expected_file = "font_family    Iosevka  \n    {{ $font }}\n{{ $size = \"12\" }}";
+
+    assert_eq!(formatted, expected_file);
+
+    Ok(())
+}
+
+#[test]
+fn format_is_idempotent() -> Result<()> {
+    let source_file = "// Fonts\nif \"a\" == $b $c = \"d\"  // trailing\n";
+
+    let formatted = format_file(source_file, true)?;
+
+    assert_eq!(format_file(&formatted, true)?, formatted);
+
+    Ok(())
+}
+
+#[test]
+fn format_condition_with_comment() -> Result<()> {
+    // The comment ends the line like it does when the line is evaluated
+    let source_file = "if   $a == \"b\" // note\n//\n";
+
+    let formatted = format_file(source_file, true)?;
+
+    assert_eq!(formatted, "if $a == \"b\" // note\n//\n");
+
+    Ok(())
+}
+
+#[test]
+fn format_reports_invalid_line() {
+    let source_file = "$valid = \"yes\"\n$invalid =\n";
+
+    let error = format_file(source_file, true).unwrap_err();
+
+    assert!(error.to_string().starts_with("Line 2:"));
+}
+
+#[test]
+fn format_lists_and_maps() -> Result<()> {
+    let source_file = "$font={family=\"Iosevka\",sizes=[\"10\",$size]}\n$count = len( $sizes )\nfor   $x in $list\nend\n";
+
+    let formatted = format_file(source_file, true)?;
+
+    let expected_file = "$font = { family = \"Iosevka\", sizes = [\"10\", $size] }\n$count = len($sizes)\nfor $x in $list\nend\n";
+
+    assert_eq!(formatted, expected_file);
+
+    Ok(())
+}
+
+#[test]
+fn format_layer_blocks() -> Result<()> {
+    let source_file = "host   [\"desktop\",\"tower\"]\n$size=\"14\"\nend\nprofile   \"work\"\nend";
+
+    let formatted = format_file(source_file, true)?;
+
+    let expected_file = "host [\"desktop\", \"tower\"]\n$size = \"14\"\nend\nprofile \"work\"\nend";
+
+    assert_eq!(formatted, expected_file);
+
+    Ok(())
+}
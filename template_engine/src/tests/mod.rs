@@ -1,4 +1,8 @@
+pub mod analysis;
+pub mod data;
 pub mod engine;
 pub mod expressions;
+pub mod format;
+pub mod paths;
 pub mod string_replacement;
 pub mod tokens;
@@ -0,0 +1,195 @@
+use std::{
+    env::temp_dir,
+    fs::{create_dir_all, remove_dir_all, write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use anyhow::Result;
+
+use crate::{
+    paths::{config_home, expand_path, portable_path, user_home, walk_files, IGNORE_FILE_NAME},
+    TemplateEngine,
+};
+
+fn engine() -> Result<TemplateEngine> {
+    let mut engine = TemplateEngine::default();
+
+    engine.evaluate_source_file("$host = \"desktop\"\n$fonts = [\"Iosevka\"]".to_string())?;
+
+    Ok(engine)
+}
+
+#[test]
+fn expand_home() {
+    assert_eq!(expand_path(Path::new("~"), None), Ok(user_home()));
+    assert_eq!(
+        expand_path(Path::new("~/.bashrc"), None),
+        Ok(user_home().join(".bashrc"))
+    );
+    assert_eq!(
+        expand_path(Path::new("$HOME/.bashrc"), None),
+        Ok(user_home().join(".bashrc"))
+    );
+    assert_eq!(
+        expand_path(Path::new("$XDG_CONFIG_HOME/kitty/kitty.conf"), None),
+        Ok(config_home().join("kitty/kitty.conf"))
+    );
+}
+
+#[test]
+fn expand_other_user_home() {
+    // Only the home of the current user is known
+    assert_eq!(
+        expand_path(Path::new("~alice/.bashrc"), None),
+        Ok(PathBuf::from("~alice/.bashrc"))
+    );
+    assert_eq!(
+        expand_path(Path::new("dots/~/file"), None),
+        Ok(PathBuf::from("dots/~/file"))
+    );
+}
+
+#[test]
+fn expand_template_variables() -> Result<()> {
+    let engine = engine()?;
+
+    assert_eq!(
+        expand_path(Path::new("out/$host.conf"), Some(&engine)),
+        Ok(PathBuf::from("out/desktop.conf"))
+    );
+    assert_eq!(
+        expand_path(Path::new("out/${host}_old.conf"), Some(&engine)),
+        Ok(PathBuf::from("out/desktop_old.conf"))
+    );
+    assert_eq!(
+        expand_path(Path::new("out/$host_old.conf"), Some(&engine)),
+        Err("Unknown variable `host_old`".to_string())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn expand_without_engine() {
+    assert_eq!(
+        expand_path(Path::new("out/$host.conf"), None),
+        Err("Unknown variable `host`".to_string())
+    );
+}
+
+#[test]
+fn expand_environment_before_variables() -> Result<()> {
+    let mut engine = engine()?;
+    engine.evaluate_source_file("$HOME = \"/somewhere/else\"".to_string())?;
+
+    assert_eq!(
+        expand_path(Path::new("$HOME/.bashrc"), Some(&engine)),
+        Ok(user_home().join(".bashrc"))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn expand_invalid_variables() -> Result<()> {
+    let engine = engine()?;
+
+    assert_eq!(
+        expand_path(Path::new("out/${host.conf"), Some(&engine)),
+        Err("`${` is never closed with `}`".to_string())
+    );
+    assert_eq!(
+        expand_path(Path::new("out/${}.conf"), Some(&engine)),
+        Err("`$` is not followed by a variable name".to_string())
+    );
+    assert_eq!(
+        expand_path(Path::new("out/$fonts"), Some(&engine)),
+        Err("Variable `fonts` is a list or map and can not be used in a path".to_string())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn expand_dollar_in_file_name() {
+    // A `$` that does not start a variable can not be told apart from a typo
+    assert_eq!(
+        expand_path(Path::new("out/price$.txt"), None),
+        Err("`$` is not followed by a variable name".to_string())
+    );
+    assert_eq!(
+        expand_path(Path::new("out/$"), None),
+        Err("`$` is not followed by a variable name".to_string())
+    );
+}
+
+#[test]
+fn portable_config_home() {
+    // `$XDG_CONFIG_HOME` defaults to `~/.config`, which takes precedence over the home it is in
+    assert_eq!(
+        portable_path(&config_home().join("kitty/kitty.conf")),
+        PathBuf::from("$XDG_CONFIG_HOME/kitty/kitty.conf")
+    );
+}
+
+#[test]
+fn portable_home() {
+    let path = user_home().join(".bashrc");
+
+    if path.starts_with(config_home()) {
+        return;
+    }
+
+    assert_eq!(portable_path(&path), PathBuf::from("~/.bashrc"));
+}
+
+#[test]
+fn portable_paths_are_kept() {
+    for path in ["~/.bashrc", "$XDG_CONFIG_HOME/kitty", "out/$host.conf"] {
+        assert_eq!(portable_path(Path::new(path)), PathBuf::from(path));
+    }
+
+    assert_eq!(
+        portable_path(Path::new("/etc/hosts")),
+        PathBuf::from("/etc/hosts")
+    );
+}
+
+#[test]
+fn portable_round_trip() {
+    for path in [
+        config_home().join("kitty/kitty.conf"),
+        user_home().join(".bashrc"),
+        PathBuf::from("/etc/hosts"),
+    ] {
+        assert_eq!(expand_path(&portable_path(&path), None), Ok(path));
+    }
+}
+
+#[test]
+fn walk_ignored_files() {
+    let home_path = temp_dir().join(format!("dotman-walk-{}", process::id()));
+    let _ = remove_dir_all(&home_path);
+    create_dir_all(home_path.join("vars/.git")).unwrap();
+
+    for file in [
+        "vars/colors.te",
+        "vars/fonts.te",
+        "vars/local.te",
+        "vars/.git/HEAD",
+    ] {
+        write(home_path.join(file), "").unwrap();
+    }
+    write(home_path.join(IGNORE_FILE_NAME), "vars/local.te\n").unwrap();
+
+    assert_eq!(
+        walk_files(&home_path.join("vars"), &home_path, |_| ()),
+        vec![
+            home_path.join("vars/colors.te"),
+            home_path.join("vars/fonts.te")
+        ]
+    );
+
+    remove_dir_all(home_path).unwrap();
+}
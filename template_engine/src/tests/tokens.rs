@@ -86,3 +86,87 @@ fn tokinize_comment() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn tokinize_with_extra_spaces() -> Result<()> {
+    let line = r#"if $test ==   "testing"   $test =   "hi""#.to_string();
+
+    let tokens = Expression::tokenize_line(line)?;
+
+    let expected_tokens = vec![
+        Token::If,
+        Token::Variable("test".to_string()),
+        Token::Condition(true),
+        Token::String("testing".to_string()),
+        Token::Variable("test".to_string()),
+        Token::Assignment,
+        Token::String("hi".to_string()),
+    ];
+
+    assert_eq!(tokens, expected_tokens);
+
+    Ok(())
+}
+
+#[test]
+fn tokinize_without_spaces() -> Result<()> {
+    let line = r#"if $test!="testing" $test="hi""#.to_string();
+
+    let tokens = Expression::tokenize_line(line)?;
+
+    let expected_tokens = vec![
+        Token::If,
+        Token::Variable("test".to_string()),
+        Token::Condition(false),
+        Token::String("testing".to_string()),
+        Token::Variable("test".to_string()),
+        Token::Assignment,
+        Token::String("hi".to_string()),
+    ];
+
+    assert_eq!(tokens, expected_tokens);
+
+    Ok(())
+}
+
+#[test]
+fn tokinize_indexed_variable() -> Result<()> {
+    let line = r#"$monitor = $monitors[0].name"#.to_string();
+
+    let tokens = Expression::tokenize_line(line)?;
+
+    let expected_tokens = vec![
+        Token::Variable("monitor".to_string()),
+        Token::Assignment,
+        Token::Variable("monitors[0].name".to_string()),
+    ];
+
+    assert_eq!(tokens, expected_tokens);
+
+    Ok(())
+}
+
+#[test]
+fn tokinize_list_and_function() -> Result<()> {
+    let line = r#"for $font in ["Iosevka", keys($fonts)]"#.to_string();
+
+    let tokens = Expression::tokenize_line(line)?;
+
+    let expected_tokens = vec![
+        Token::For,
+        Token::Variable("font".to_string()),
+        Token::In,
+        Token::OpenBracket,
+        Token::String("Iosevka".to_string()),
+        Token::Comma,
+        Token::Identifier("keys".to_string()),
+        Token::OpenParenthesis,
+        Token::Variable("fonts".to_string()),
+        Token::CloseParenthesis,
+        Token::CloseBracket,
+    ];
+
+    assert_eq!(tokens, expected_tokens);
+
+    Ok(())
+}